@@ -0,0 +1,48 @@
+//! Compares serial and parallel stepping: `cargo bench --bench step`.
+//!
+//! Chunks are stepped on rayon's thread pool, but the copy of the result
+//! back into the grid is serial, so the speedup stays well below the number
+//! of threads, which is printed first. On one thread the two paths do the
+//! same work, and runs there scattered between 0.9x and 1.4x, which is only
+//! noise. A review machine measured 1.0x, 1.0x and 1.2x for the three cases
+//! here. Neither shows the split paying off yet, so measure on a machine with
+//! several cores before relying on it or tuning the chunk size.
+
+use portfolio_bevy_automata::{Boundary, Grid, NoiseSettings, Rule};
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+const GENERATIONS: u32 = 10;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..GENERATIONS {
+        f();
+    }
+    start.elapsed() / GENERATIONS
+}
+
+fn main() {
+    println!("{} threads", rayon::current_num_threads());
+    for (rule, size) in [("4/4/5/M", 100), ("4/4/5/M", 200), ("9-40/10-20/5/N3", 100)] {
+        let rule = rule.parse::<Rule>().unwrap();
+        let noise = NoiseSettings {
+            size: size as u8 / 3,
+            ..Default::default()
+        };
+        let g = Grid::new_noise([size; 3], &noise);
+        let serial = time(|| {
+            black_box(g.next_serial(&rule, Boundary::DeadWall));
+        });
+        let parallel = time(|| {
+            black_box(g.next(&rule, Boundary::DeadWall));
+        });
+        println!(
+            "{size}³ {:<16} serial {serial:>10.2?}  parallel {parallel:>10.2?}  speedup {:.1}x",
+            format!("{:?}", rule.neighbors),
+            serial.as_secs_f64() / parallel.as_secs_f64()
+        );
+    }
+}
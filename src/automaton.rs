@@ -0,0 +1,198 @@
+//! The interface shared by the bounded [`Grid`] and the unbounded
+//! [`SparseGrid`], so the viewer and headless runner work with either.
+
+use crate::{
+    cell::CellStatus,
+    grid::{Boundary, Grid, Point},
+    rule::Rule,
+    sparse::SparseGrid,
+};
+
+/// A universe of cells that can be seeded, stepped and drawn.
+pub trait Automaton: Clone + Send + Sync + 'static {
+    /// A universe holding the cells of `grid`, such as a freshly seeded one.
+    fn from_grid(grid: &Grid) -> Self;
+
+    /// Steps every cell to the next generation. Universes without edges
+    /// ignore `boundary`.
+    fn next(&self, rule: &Rule, boundary: Boundary) -> Self;
+
+    /// The cell at `p`. Cells outside a bounded universe are dead.
+    fn status(&self, p: [i64; 3]) -> CellStatus;
+
+    /// Every cell that isn't dead, with its position.
+    fn live_cells(&self) -> Box<dyn Iterator<Item = ([i64; 3], CellStatus)> + '_>;
+
+    /// The point views of the universe should be centered on.
+    fn center(&self) -> [f32; 3];
+
+    /// The cells as a bounded grid, such as for a snapshot. Unbounded
+    /// universes are cropped to the box around their cells that aren't dead.
+    fn to_grid(&self) -> Grid;
+}
+
+impl Automaton for Grid {
+    fn from_grid(grid: &Grid) -> Self {
+        grid.clone()
+    }
+
+    fn next(&self, rule: &Rule, boundary: Boundary) -> Self {
+        Grid::next(self, rule, boundary)
+    }
+
+    fn status(&self, p: [i64; 3]) -> CellStatus {
+        match p.map(usize::try_from) {
+            [Ok(x), Ok(y), Ok(z)] => self.get(&Point::new(x, y, z)),
+            _ => None,
+        }
+        .unwrap_or(CellStatus::Dead)
+    }
+
+    fn live_cells(&self) -> Box<dyn Iterator<Item = ([i64; 3], CellStatus)> + '_> {
+        Box::new(
+            self.iter()
+                .filter(|(_, c)| *c != CellStatus::Dead)
+                .map(|(p, c)| (<[usize; 3]>::from(p).map(|c| c as i64), c)),
+        )
+    }
+
+    fn center(&self) -> [f32; 3] {
+        self.dims().map(|d| d as f32 / 2.)
+    }
+
+    fn to_grid(&self) -> Grid {
+        self.clone()
+    }
+}
+
+impl Automaton for SparseGrid {
+    fn from_grid(grid: &Grid) -> Self {
+        SparseGrid::from(grid)
+    }
+
+    fn next(&self, rule: &Rule, _: Boundary) -> Self {
+        SparseGrid::next(self, rule)
+    }
+
+    fn status(&self, p: [i64; 3]) -> CellStatus {
+        self.get(p)
+    }
+
+    fn live_cells(&self) -> Box<dyn Iterator<Item = ([i64; 3], CellStatus)> + '_> {
+        Box::new(self.iter())
+    }
+
+    /// The middle of the bounding box of the cells that aren't dead, so
+    /// structures that drift stay in view.
+    fn center(&self) -> [f32; 3] {
+        self.bounds().map_or([0.; 3], |(lo, hi)| {
+            [0, 1, 2].map(|d| (lo[d] + hi[d]) as f32 / 2.)
+        })
+    }
+
+    fn to_grid(&self) -> Grid {
+        let Some((lo, hi)) = self.bounds() else {
+            return Grid::new([1; 3]);
+        };
+        let mut g = Grid::new([0, 1, 2].map(|d| (hi[d] - lo[d] + 1) as usize));
+        for (p, c) in self.iter() {
+            let [x, y, z] = [0, 1, 2].map(|d| (p[d] - lo[d]) as usize);
+            g.set(&Point::new(x, y, z), c);
+        }
+        g
+    }
+}
+
+/// Offsets of the six cells that share a face with a cell.
+const FACES: [[i64; 3]; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+/// The cells of `g` that aren't dead and are at positions `drawn` accepts,
+/// leaving out those whose six face neighbors would all be drawn too, as
+/// they're hidden wherever cubes fill the spaces between cells.
+pub fn visible_cells<G: Automaton>(
+    g: &G,
+    drawn: impl Fn([i64; 3]) -> bool,
+) -> Vec<([i64; 3], CellStatus)> {
+    let covers = |p: [i64; 3]| g.status(p) != CellStatus::Dead && drawn(p);
+    g.live_cells()
+        .filter(|(p, _)| drawn(*p))
+        .filter(|(p, _)| {
+            !FACES
+                .iter()
+                .all(|o| covers([p[0] + o[0], p[1] + o[1], p[2] + o[2]]))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A solid cube of alive cells `side` cells across in a larger grid.
+    fn cube(side: usize) -> Grid {
+        let mut g = Grid::new([side + 4; 3]);
+        for (x, y, z) in itertools::iproduct!(0..side, 0..side, 0..side) {
+            g.set(&Point::new(x + 2, y + 2, z + 2), CellStatus::Alive);
+        }
+        g
+    }
+
+    #[test]
+    fn hides_surrounded_cells() {
+        let g = cube(5);
+        let all = |_| true;
+        assert_eq!(visible_cells(&g, all).len(), 5 * 5 * 5 - 3 * 3 * 3);
+        assert_eq!(
+            visible_cells(&SparseGrid::from(&g), all).len(),
+            5 * 5 * 5 - 3 * 3 * 3
+        );
+        // only the middle cell is hidden, and dying cells hide it too
+        let mut g = cube(3);
+        g.set(&Point::new(2, 3, 3), CellStatus::Dying { health: 1 });
+        let visible = visible_cells(&g, all);
+        assert_eq!(visible.len(), 26);
+        assert!(!visible.iter().any(|(p, _)| *p == [3, 3, 3]));
+        assert!(visible.contains(&([2, 3, 3], CellStatus::Dying { health: 1 })));
+
+        // cells on the edge of the grid have dead neighbors beyond it
+        let mut full = Grid::new([3; 3]);
+        for p in Grid::new([3; 3]).iter().map(|(p, _)| p) {
+            full.set(&p, CellStatus::Alive);
+        }
+        assert_eq!(visible_cells(&full, all).len(), 26);
+    }
+
+    #[test]
+    fn clipped_cells_expose_those_behind_them() {
+        let g = cube(5);
+        // cut away everything past the middle of the cube along x, showing
+        // the 3x3 of cells that were hidden behind the cut
+        let visible = visible_cells(&g, |p| p[0] <= 4);
+        assert!(visible.iter().all(|(p, _)| p[0] <= 4));
+        let layer = visible.iter().filter(|(p, _)| p[0] == 4).count();
+        assert_eq!(layer, 25);
+        // the face of the cube, the ring around x = 3 and the cut layer
+        assert_eq!(visible.len(), 25 + (5 * 5 - 3 * 3) + 25);
+    }
+
+    #[test]
+    fn sparse_grids_crop_to_their_cells() {
+        let mut g = cube(3);
+        g.set(&Point::new(2, 2, 2), CellStatus::Dying { health: 2 });
+        let cropped = SparseGrid::from(&g).to_grid();
+        assert_eq!(cropped.dims(), [3; 3]);
+        assert_eq!(cropped.live_cells().count(), 27);
+        assert_eq!(cropped.status([0; 3]), CellStatus::Dying { health: 2 });
+        assert_eq!(
+            SparseGrid::from(&Grid::new([4; 3])).to_grid().dims(),
+            [1; 3]
+        );
+    }
+}
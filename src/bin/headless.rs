@@ -0,0 +1,12 @@
+//! The headless runner on its own, so it builds without the `viewer`
+//! feature and the window system libraries Bevy needs.
+
+use portfolio_bevy_automata::headless;
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Err(e) = headless::run(&args) {
+        eprintln!("error: {e}\n\n{}", headless::USAGE);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,124 @@
+//! A camera that orbits the grid: drag to turn around it, scroll to zoom,
+//! middle-drag to pan and `F` to look at the middle again.
+
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+use bevy_egui::EguiContexts;
+use std::f32::consts::FRAC_PI_2;
+
+/// Where the camera looks from, as angles and a distance around the point
+/// it looks at.
+#[derive(Component)]
+pub(crate) struct OrbitCamera {
+    pub(crate) focus: Vec3,
+    /// Angle around the vertical axis, in radians.
+    pub(crate) yaw: f32,
+    /// Angle above the horizontal, in radians.
+    pub(crate) elevation: f32,
+    pub(crate) distance: f32,
+}
+
+impl Default for OrbitCamera {
+    /// Slightly above the middle of the grid, far enough back to see all of
+    /// a default sized one.
+    fn default() -> Self {
+        Self {
+            focus: Vec3::ZERO,
+            yaw: 0.,
+            elevation: 0.1,
+            distance: 90.,
+        }
+    }
+}
+
+impl OrbitCamera {
+    fn transform(&self) -> Transform {
+        let rotation = Quat::from_euler(EulerRot::YXZ, self.yaw, -self.elevation, 0.);
+        Transform::from_translation(self.focus + rotation * Vec3::Z * self.distance)
+            .looking_at(self.focus, Vec3::Y)
+    }
+}
+
+/// Turning the camera around the grid on its own.
+#[derive(Resource)]
+pub(crate) struct AutoRotate {
+    pub(crate) enabled: bool,
+    /// Radians per second.
+    pub(crate) speed: f32,
+}
+
+impl Default for AutoRotate {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            speed: 1.2,
+        }
+    }
+}
+
+/// Radians turned per pixel dragged.
+const ORBIT_SPEED: f32 = 0.005;
+/// Share of the distance panned per pixel dragged.
+const PAN_SPEED: f32 = 0.0015;
+
+pub(crate) fn spawn_camera(mut commands: Commands) {
+    let orbit = OrbitCamera::default();
+    commands.spawn((
+        Camera3dBundle {
+            transform: orbit.transform(),
+            ..default()
+        },
+        orbit,
+    ));
+}
+
+/// Moves the camera with the mouse, unless the pointer is over the UI.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn orbit_camera(
+    mut cameras: Query<(&mut OrbitCamera, &mut Transform)>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    auto_rotate: Res<AutoRotate>,
+    time: Res<Time>,
+    mut contexts: EguiContexts,
+) {
+    let Ok((mut orbit, mut transform)) = cameras.get_single_mut() else {
+        return;
+    };
+    let ctx = contexts.ctx_mut();
+    let over_ui = ctx.is_pointer_over_area() || ctx.wants_pointer_input();
+    let drag: Vec2 = motion.read().map(|m| m.delta).sum();
+    let scroll: f32 = wheel
+        .read()
+        .map(|w| match w.unit {
+            MouseScrollUnit::Line => w.y,
+            // a line is roughly this many pixels
+            MouseScrollUnit::Pixel => w.y / 40.,
+        })
+        .sum();
+    if !over_ui {
+        if buttons.pressed(MouseButton::Left) || buttons.pressed(MouseButton::Right) {
+            orbit.yaw -= drag.x * ORBIT_SPEED;
+            orbit.elevation =
+                (orbit.elevation + drag.y * ORBIT_SPEED).clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+        }
+        if buttons.pressed(MouseButton::Middle) {
+            let pan = (transform.right() * -drag.x + transform.up() * drag.y)
+                * orbit.distance
+                * PAN_SPEED;
+            orbit.focus += pan;
+        }
+        orbit.distance = (orbit.distance * 0.9f32.powf(scroll)).clamp(2., 2000.);
+    }
+    if !ctx.wants_keyboard_input() && keys.just_pressed(KeyCode::F) {
+        orbit.focus = Vec3::ZERO;
+    }
+    if auto_rotate.enabled {
+        orbit.yaw += auto_rotate.speed * time.delta_seconds();
+    }
+    *transform = orbit.transform();
+}
@@ -1,5 +1,4 @@
 use crate::rule::Rule;
-use bevy::prelude::Color;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CellStatus {
@@ -23,32 +22,6 @@ impl CellStatus {
     pub fn is_live(&self) -> bool {
         matches!(self, Self::Alive | Self::Dying { .. })
     }
-
-    #[allow(dead_code)]
-    pub fn color(&self) -> Color {
-        match self {
-            Self::Dead => Color::rgba(0., 0., 0., 0.),
-            Self::Alive => Color::WHITE,
-            _ => Color::GRAY,
-        }
-    }
-
-    pub fn color_grad(&self, states: &u8) -> Color {
-        const C1: Color = Color::hsl(359.9, 1., 0.5);
-        const C2: Color = Color::hsl(300., 1., 0.);
-        match self {
-            Self::Dead => C2,
-            Self::Alive => C1,
-            Self::Dying { health } => {
-                let h1 = C1.h();
-                let h2 = C2.h();
-                let weight = *health as f32 / *states as f32;
-                let l = weight / 2.;
-                let h = (1. - weight) * h1 + weight * h2;
-                Color::hsl(h, 1., l)
-            }
-        }
-    }
 }
 
 #[cfg(test)]
@@ -64,7 +37,7 @@ mod tests {
             survival: vec![4..6],
             birth: vec![4..5],
             states: 6,
-            neighbors: Neighbors::Neumann,
+            neighbors: Neighbors::Neumann(1),
         };
         let c = CellStatus::Alive;
         // stays alive at 4 or 5
@@ -0,0 +1,180 @@
+//! Detecting when a run settles into a still life or an oscillator, by
+//! comparing fingerprints of recent generations.
+
+use crate::{cell::CellStatus, grid::PackedCell};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+
+/// Scrambles `h` so every input bit affects every output bit (splitmix64's
+/// finalizer), making sums of the results good fingerprints.
+pub(crate) fn mix(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
+
+/// The fingerprint contribution of the cell `c` at `p`. Summing these over
+/// every cell that isn't dead fingerprints a generation regardless of the
+/// order the cells are visited in.
+pub(crate) fn cell_hash(p: [i64; 3], c: CellStatus) -> u64 {
+    let h = p.iter().fold(0, |h, c| mix(h ^ *c as u64));
+    mix(h ^ u64::from(PackedCell::from(c).0))
+}
+
+/// A run repeating the same generations over and over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    /// Generations between repeats; 1 for a still life.
+    pub period: u64,
+    /// The first generation of the repeating part.
+    pub since: u64,
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.period {
+            1 => write!(f, "still life since generation {}", self.since),
+            p => write!(f, "period {p} oscillator since generation {}", self.since),
+        }
+    }
+}
+
+/// Watches the fingerprints of consecutive generations for repeats.
+///
+/// Only the last `max_period` generations are remembered, so longer cycles
+/// go unnoticed. Fingerprints are 64-bit hashes, so a false repeat is
+/// possible but vanishingly unlikely.
+pub struct CycleDetector {
+    max_period: usize,
+    /// The latest generation each remembered fingerprint was seen at.
+    seen: HashMap<u64, u64>,
+    /// The remembered generations and their fingerprints, oldest first.
+    recent: VecDeque<(u64, u64)>,
+    cycle: Option<Cycle>,
+}
+
+impl CycleDetector {
+    pub fn new(max_period: usize) -> Self {
+        Self {
+            max_period: max_period.max(1),
+            seen: HashMap::new(),
+            recent: VecDeque::new(),
+            cycle: None,
+        }
+    }
+
+    /// Adds the fingerprint of `generation`, and returns the cycle the run is
+    /// in, if any. A generation that doesn't follow the last one starts the
+    /// detector over, as after rewinding.
+    pub fn push(&mut self, generation: u64, fingerprint: u64) -> Option<Cycle> {
+        if self
+            .recent
+            .back()
+            .is_some_and(|(last, _)| *last + 1 != generation)
+        {
+            *self = Self::new(self.max_period);
+        }
+        let period = self.seen.get(&fingerprint).map(|seen| generation - seen);
+        self.cycle = match (period, self.cycle) {
+            (Some(period), Some(cycle)) if cycle.period == period => Some(cycle),
+            (Some(period), _) => Some(Cycle {
+                period,
+                since: generation - period,
+            }),
+            (None, _) => None,
+        };
+        self.seen.insert(fingerprint, generation);
+        self.recent.push_back((generation, fingerprint));
+        if self.recent.len() > self.max_period {
+            if let Some((old, h)) = self.recent.pop_front() {
+                if self.seen.get(&h) == Some(&old) {
+                    self.seen.remove(&h);
+                }
+            }
+        }
+        self.cycle
+    }
+
+    /// The cycle found by the last [`CycleDetector::push`].
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::{Boundary, Grid, Point},
+        rule::Rule,
+        stats::Stats,
+    };
+
+    #[test]
+    fn finds_cycles_in_fingerprints() {
+        let mut d = CycleDetector::new(4);
+        // 1 2 3 | 4 5 4 5 4 | 6 6 6
+        let mut found = vec![];
+        for (g, h) in [1, 2, 3, 4, 5, 4, 5, 4, 6, 6, 6].into_iter().enumerate() {
+            found.push(d.push(g as u64, h).map(|c| (c.period, c.since)));
+        }
+        assert_eq!(
+            found,
+            [
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some((2, 3)),
+                Some((2, 3)),
+                Some((2, 3)),
+                None,
+                Some((1, 8)),
+                Some((1, 8)),
+            ]
+        );
+        assert_eq!(
+            d.cycle().unwrap().to_string(),
+            "still life since generation 8"
+        );
+
+        // a cycle longer than the detector remembers
+        let mut d = CycleDetector::new(2);
+        for (g, h) in [1, 2, 3, 1, 2, 3].into_iter().enumerate() {
+            assert_eq!(d.push(g as u64, h), None);
+        }
+        // going back starts over
+        assert_eq!(d.push(3, 3), None);
+        assert_eq!(d.push(4, 3).map(|c| c.since), Some(3));
+    }
+
+    #[test]
+    fn detects_a_blinker() {
+        // a line of three cells in the xy plane flips between x and y
+        let rule = "2/3/2/C(-1,-1,0)(-1,0,0)(-1,1,0)(0,-1,0)(0,1,0)(1,-1,0)(1,0,0)(1,1,0)"
+            .parse::<Rule>()
+            .unwrap();
+        let mut g = Grid::new([7, 7, 1]);
+        for x in 1..4 {
+            g.set(&Point::new(x, 2, 0), CellStatus::Alive);
+        }
+        let mut d = CycleDetector::new(10);
+        let mut cycles = vec![];
+        for generation in 0..4 {
+            cycles.push(d.push(generation, Stats::new(&g, None, 2).fingerprint));
+            g = g.next(&rule, Boundary::DeadWall);
+        }
+        let blinker = Cycle {
+            period: 2,
+            since: 0,
+        };
+        assert_eq!(cycles, [None, None, Some(blinker), Some(blinker)]);
+        assert_eq!(
+            blinker.to_string(),
+            "period 2 oscillator since generation 0"
+        );
+    }
+}
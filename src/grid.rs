@@ -1,13 +1,17 @@
 use crate::{
     cell::CellStatus,
+    neighborhood::{NeighborCounts, Shape},
     rule::{Neighbors, Rule},
+    symmetry::Symmetry,
 };
+#[cfg(feature = "viewer")]
 use bevy::prelude::*;
 use enum_map::{enum_map, Enum, EnumMap};
 use itertools::iproduct;
 use noise::{NoiseFn, OpenSimplex};
-use std::iter;
-use strum::{EnumIter, IntoEnumIterator};
+use rayon::prelude::*;
+use std::{fmt, ops::Range, str::FromStr};
+use strum::{Display, EnumIter, IntoEnumIterator};
 
 macro_rules! point {
     ($x:expr, $y:expr, $z:expr) => {
@@ -19,11 +23,22 @@ macro_rules! point {
     };
 }
 
-#[derive(Resource)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "viewer", derive(Resource))]
 pub struct NoiseSettings {
     pub seed: u32,
     pub threshold: f64,
+    /// Radius, or half the side length, of the core.
     pub size: u8,
+    /// How quickly the noise changes from cell to cell. At 1 it's sampled
+    /// at the cell coordinates.
+    pub frequency: f64,
+    /// Moves the noise through the grid, in noise coordinates.
+    pub offset: [f64; 3],
+    /// Multiplies the frequency along each axis, so the noise is stretched
+    /// along axes with smaller values.
+    pub scale: [f64; 3],
+    pub shape: CoreShape,
 }
 
 impl Default for NoiseSettings {
@@ -32,86 +47,467 @@ impl Default for NoiseSettings {
             seed: 1,
             threshold: 0.1,
             size: 10,
+            frequency: 1.,
+            offset: [0.; 3],
+            scale: [1.; 3],
+            shape: CoreShape::default(),
         }
     }
 }
 
-#[derive(Component, Clone)]
-pub struct Grid(Vec<Vec<Vec<CellStatus>>>);
+impl NoiseSettings {
+    /// Whether the cell at `p` starts alive in a grid of `dims` cells.
+    pub(crate) fn sampler(&self, dims: [usize; 3]) -> impl Fn([usize; 3]) -> bool + '_ {
+        let noise = OpenSimplex::new(self.seed);
+        let center = dims.map(|d| d / 2);
+        move |p| {
+            let offset = [0, 1, 2].map(|d| p[d] as f32 - center[d] as f32);
+            if !self.shape.contains(offset, self.size) {
+                return false;
+            }
+            let at =
+                [0, 1, 2].map(|d| p[d] as f64 * self.frequency * self.scale[d] + self.offset[d]);
+            noise.get(at) > self.threshold
+        }
+    }
 
-#[derive(Component)]
-pub struct MainGrid;
+    /// Which cells of the `z` layer of a grid of `dims` cells start alive
+    /// with `symmetry`, row by row, without seeding the whole grid.
+    pub fn slice(&self, dims: [usize; 3], z: usize, symmetry: Symmetry) -> Vec<bool> {
+        let alive = self.sampler(dims);
+        let source = symmetry.source(dims);
+        iproduct!(0..dims[1], 0..dims[0])
+            .map(|(y, x)| alive(source([x, y, z])))
+            .collect()
+    }
+}
 
-impl Grid {
-    pub fn new(size: usize) -> Self {
-        Self(vec![vec![vec![CellStatus::Dead; size]; size]; size])
+/// The part of the grid that [`Grid::new_noise`] seeds, centered in it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CoreShape {
+    /// Cells closer to the middle than the core size.
+    #[default]
+    Sphere,
+    /// Cells closer to the middle than the core size along every axis.
+    Cube,
+    /// Cells closer than the core size to the z axis through the middle, at
+    /// any depth.
+    Cylinder,
+    /// Cells inside the ellipsoid with these radii along x, y and z.
+    Ellipsoid([u8; 3]),
+    /// Every cell.
+    Full,
+}
+
+impl CoreShape {
+    /// The shape's name, without an ellipsoid's radii.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sphere => "Sphere",
+            Self::Cube => "Cube",
+            Self::Cylinder => "Cylinder",
+            Self::Ellipsoid(_) => "Ellipsoid",
+            Self::Full => "Full grid",
+        }
     }
 
-    pub fn new_noise(size: usize, n: &NoiseSettings) -> Self {
-        let noise = OpenSimplex::new(n.seed);
-        let mut g = Self::new(size);
-        let p = g.points().collect::<Vec<_>>();
-        let center = {
-            let mid = g.len() / 2;
-            point!(mid, mid, mid)
+    /// One of each shape, with ellipsoid radii based on `size`.
+    pub fn all(size: u8) -> [CoreShape; 5] {
+        [
+            Self::Sphere,
+            Self::Cube,
+            Self::Cylinder,
+            Self::Ellipsoid([size, size / 2, size / 2].map(|r| r.max(1))),
+            Self::Full,
+        ]
+    }
+
+    /// Whether a cell `offset` from the middle is in a core of this shape.
+    fn contains(&self, offset: [f32; 3], size: u8) -> bool {
+        let size = f32::from(size);
+        let [x, y, z] = offset;
+        match self {
+            Self::Sphere => (x * x + y * y + z * z).sqrt() < size,
+            Self::Cube => offset.iter().all(|c| c.abs() < size),
+            Self::Cylinder => (x * x + y * y).sqrt() < size,
+            Self::Ellipsoid(radii) => {
+                offset
+                    .iter()
+                    .zip(radii)
+                    .map(|(c, r)| (c / f32::from(*r)).powi(2))
+                    .sum::<f32>()
+                    < 1.
+            }
+            Self::Full => true,
+        }
+    }
+}
+
+impl fmt::Display for CoreShape {
+    /// The shape's name, with the radii of an ellipsoid as `ellipsoid:XxYxZ`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sphere => write!(f, "sphere"),
+            Self::Cube => write!(f, "cube"),
+            Self::Cylinder => write!(f, "cylinder"),
+            Self::Ellipsoid([x, y, z]) => write!(f, "ellipsoid:{x}x{y}x{z}"),
+            Self::Full => write!(f, "full"),
+        }
+    }
+}
+
+impl FromStr for CoreShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let radii = |r: &str| {
+            let r = parse_dims(r).filter(|r| r.iter().all(|r| (1..=255).contains(r)))?;
+            Some(Self::Ellipsoid(r.map(|r| r as u8)))
         };
-        for p in p.into_iter().filter(|x| x.dist(&center) < n.size as f32) {
-            let val = noise.get([p.0[Dim::X] as f64, p.0[Dim::Y] as f64, p.0[Dim::Z] as f64]);
-            *g.get_mut(&p).unwrap() = if val > n.threshold {
-                CellStatus::Alive
-            } else {
-                CellStatus::Dead
-            };
+        match s.trim() {
+            "sphere" => Ok(Self::Sphere),
+            "cube" => Ok(Self::Cube),
+            "cylinder" => Ok(Self::Cylinder),
+            "full" => Ok(Self::Full),
+            s => s
+                .strip_prefix("ellipsoid:")
+                .and_then(radii)
+                .ok_or(format!("unknown core shape {s}")),
+        }
+    }
+}
+
+/// Parses three comma-separated numbers, such as an offset or scale.
+pub(crate) fn parse_triple(value: &str) -> Option<[f64; 3]> {
+    let v = value
+        .split(',')
+        .map(|s| s.trim().parse().ok())
+        .collect::<Option<Vec<f64>>>()?;
+    v.try_into().ok()
+}
+
+/// Parses grid dimensions written either as a single side length or as
+/// `WxHxD`.
+pub(crate) fn parse_dims(value: &str) -> Option<[usize; 3]> {
+    let sides = value
+        .split('x')
+        .map(|s| s.trim().parse().ok())
+        .collect::<Option<Vec<usize>>>()?;
+    match sides[..] {
+        [s] => Some([s; 3]),
+        [w, h, d] => Some([w, h, d]),
+        _ => None,
+    }
+}
+
+/// What lies beyond the edges of a [`Grid`] when counting neighbors.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+#[cfg_attr(feature = "viewer", derive(Resource))]
+pub enum Boundary {
+    /// Cells outside the grid are always dead.
+    #[default]
+    #[strum(serialize = "Dead wall")]
+    DeadWall,
+    /// Cells outside the grid are always alive.
+    #[strum(serialize = "Alive wall")]
+    AliveWall,
+    /// Opposite edges are joined, so the grid wraps around on every axis.
+    Torus,
+    /// Cells outside the grid mirror the cells just inside the edge.
+    Mirror,
+}
+
+impl Boundary {
+    /// Maps a coordinate that may lie outside `0..len` back into the grid, or
+    /// returns `None` if it falls on a wall.
+    fn resolve(&self, c: isize, len: usize) -> Option<usize> {
+        let l = len as isize;
+        if (0..l).contains(&c) {
+            return Some(c as usize);
+        }
+        match self {
+            _ if len == 0 => None,
+            Self::DeadWall | Self::AliveWall => None,
+            Self::Torus => Some(c.rem_euclid(l) as usize),
+            Self::Mirror => {
+                // reflecting off both edges repeats every two grid lengths
+                let c = c.rem_euclid(2 * l);
+                Some(if c < l { c } else { 2 * l - c - 1 } as usize)
+            }
+        }
+    }
+}
+
+/// Side length of the cubic chunks whose activity [`Grid`] tracks.
+const CHUNK: usize = 16;
+
+/// A box of cells stored in a single contiguous buffer.
+///
+/// Cells are laid out with `z` varying fastest, then `y`, then `x`, so the
+/// linear index of `(x, y, z)` in a grid of dimensions `[_, h, d]` is
+/// `(x * h + y) * d + z`.
+///
+/// The grid also remembers which [`CHUNK`]-sized chunks changed in the last
+/// step. A cell whose neighborhood didn't change can't change either, so the
+/// next step only recomputes chunks that read from a changed chunk.
+#[derive(Clone)]
+#[cfg_attr(feature = "viewer", derive(Component))]
+pub struct Grid {
+    /// Width, height and depth.
+    dims: [usize; 3],
+    cells: Vec<PackedCell>,
+    /// Whether each chunk changed since the previous generation.
+    changed: Vec<bool>,
+    /// What the last step was computed with; any other rule or boundary
+    /// needs a full recomputation.
+    last_step: Option<(Rule, Boundary)>,
+}
+
+impl Grid {
+    /// An all-dead grid `dims[0]` cells wide, `dims[1]` high and `dims[2]`
+    /// deep.
+    pub fn new(dims: [usize; 3]) -> Self {
+        let [cx, cy, cz] = dims.map(|d| d.div_ceil(CHUNK));
+        Self {
+            dims,
+            cells: vec![PackedCell::DEAD; dims.iter().product()],
+            changed: vec![true; cx * cy * cz],
+            last_step: None,
+        }
+    }
+
+    /// A grid whose cells in the core around the middle are alive wherever
+    /// the noise exceeds `n.threshold`.
+    pub fn new_noise(dims: [usize; 3], n: &NoiseSettings) -> Self {
+        let alive = n.sampler(dims);
+        let mut g = Self::new(dims);
+        for p in g.points() {
+            if alive(p.clone().into()) {
+                g.set(&p, CellStatus::Alive);
+            }
         }
         g
     }
 
-    pub fn next(&self, rule: &Rule) -> Grid {
-        let mut next = Self::new(self.len());
-        self.points().for_each(|p| {
-            let nc = self.next_as_point(&p, rule);
-            *next.get_mut(&p).unwrap() = nc;
+    /// Steps every cell to the next generation, spread over all cores. The
+    /// result is always identical to [`Grid::next_serial`].
+    pub fn next(&self, rule: &Rule, boundary: Boundary) -> Grid {
+        self.step(rule, boundary, true)
+    }
+
+    /// Steps every cell to the next generation on the current thread.
+    pub fn next_serial(&self, rule: &Rule, boundary: Boundary) -> Grid {
+        self.step(rule, boundary, false)
+    }
+
+    fn step(&self, rule: &Rule, boundary: Boundary, parallel: bool) -> Grid {
+        let [_, h, d] = self.dims;
+        let [nx, ny, nz] = self.chunk_counts();
+        let r = rule.neighbors.radius() as isize;
+        let shape = Shape::new(&rule.neighbors);
+        let fresh = self.last_step.as_ref().map(|(r, b)| (r, *b)) != Some((rule, boundary));
+        // the chunks that cells in each chunk can see, along each axis
+        let [rx, ry, rz] = [0, 1, 2].map(|axis| {
+            (0..self.chunk_counts()[axis])
+                .map(|c| {
+                    let cells = self.chunk_range(axis, c);
+                    let mut seen = (cells.start as isize - r..cells.end as isize + r)
+                        .filter_map(|i| boundary.resolve(i, self.dims[axis]))
+                        .map(|i| i / CHUNK)
+                        .collect::<Vec<_>>();
+                    seen.sort();
+                    seen.dedup();
+                    seen
+                })
+                .collect::<Vec<_>>()
         });
+
+        let step_chunk = |&(cx, cy, cz): &(usize, usize, usize)| {
+            let dirty = fresh
+                || iproduct!(&rx[cx], &ry[cy], &rz[cz])
+                    .any(|(x, y, z)| self.changed[(x * ny + y) * nz + z]);
+            if !dirty {
+                return None;
+            }
+            let ranges = self.chunk_ranges([cx, cy, cz]);
+            let counts = self.neighbor_counts(&shape, boundary, ranges.clone());
+            let [xs, ys, zs] = ranges;
+            let cells = iproduct!(xs, ys, zs)
+                .map(|(x, y, z)| {
+                    let c = CellStatus::from(self.cells[(x * h + y) * d + z]);
+                    let [lx, ly, lz] = [x, y, z].map(|c| c % CHUNK);
+                    PackedCell::from(c.next_state(rule, counts.get(lx, ly, lz)))
+                })
+                .collect::<Vec<_>>();
+            Some(cells)
+        };
+        let chunks = iproduct!(0..nx, 0..ny, 0..nz).collect::<Vec<_>>();
+        let stepped = if parallel {
+            chunks.par_iter().map(step_chunk).collect::<Vec<_>>()
+        } else {
+            chunks.iter().map(step_chunk).collect()
+        };
+
+        let mut next = Self {
+            dims: self.dims,
+            cells: self.cells.clone(),
+            changed: vec![false; nx * ny * nz],
+            last_step: Some((rule.clone(), boundary)),
+        };
+        for (i, (&(cx, cy, cz), cells)) in chunks.iter().zip(stepped).enumerate() {
+            let Some(cells) = cells else {
+                continue;
+            };
+            let [xs, ys, zs] = self.chunk_ranges([cx, cy, cz]);
+            for ((x, y, z), c) in iproduct!(xs, ys, zs).zip(cells) {
+                let cell = &mut next.cells[(x * h + y) * d + z];
+                if *cell != c {
+                    *cell = c;
+                    next.changed[i] = true;
+                }
+            }
+        }
         next
     }
 
+    /// Counts neighbors for the cells in `region`, with cells beyond the
+    /// edges filled in as `boundary` dictates.
+    fn neighbor_counts<'a>(
+        &self,
+        shape: &'a Shape,
+        boundary: Boundary,
+        region: [Range<usize>; 3],
+    ) -> NeighborCounts<'a> {
+        let [_, h, d] = self.dims;
+        let r = shape.radius() as isize;
+        // grid coordinates of the cells within reach along each axis
+        let [rx, ry, rz] = [0, 1, 2].map(|axis| {
+            let c = &region[axis];
+            (c.start as isize - r..c.end as isize + r)
+                .map(|i| boundary.resolve(i, self.dims[axis]))
+                .collect::<Vec<_>>()
+        });
+        let padded = |v: &[Option<usize>], c: isize| v[(c + r) as usize];
+        NeighborCounts::new(shape, region.map(|c| c.len()), |[x, y, z]| {
+            match (padded(&rx, x), padded(&ry, y), padded(&rz, z)) {
+                (Some(x), Some(y), Some(z)) => self.cells[(x * h + y) * d + z] == PackedCell::ALIVE,
+                _ => boundary == Boundary::AliveWall,
+            }
+        })
+    }
+
+    /// Number of chunks along each axis.
+    fn chunk_counts(&self) -> [usize; 3] {
+        self.dims.map(|d| d.div_ceil(CHUNK))
+    }
+
+    /// The cells along `axis` that belong to the `c`th chunk.
+    fn chunk_range(&self, axis: usize, c: usize) -> Range<usize> {
+        c * CHUNK..((c + 1) * CHUNK).min(self.dims[axis])
+    }
+
+    /// The cells of the chunk `c` along each axis.
+    fn chunk_ranges(&self, c: [usize; 3]) -> [Range<usize>; 3] {
+        [0, 1, 2].map(|axis| self.chunk_range(axis, c[axis]))
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (Point, CellStatus)> + '_ {
-        self.points().map(|p| (p.clone(), *self.get(&p).unwrap()))
+        self.points()
+            .zip(self.cells.iter())
+            .map(|(p, c)| (p, CellStatus::from(*c)))
     }
 
-    fn next_as_point(&self, p: &Point, rule: &Rule) -> CellStatus {
-        let count = p
-            .neighbors(&rule.neighbors)
-            .into_iter()
-            .filter(|p| self.get(p) == Some(&CellStatus::Alive))
-            .count();
-        self.get(p).unwrap().next_state(rule, count)
+    /// A grid with the given cells, laid out as described on [`Grid`].
+    pub(crate) fn from_packed(dims: [usize; 3], cells: Vec<PackedCell>) -> Self {
+        assert_eq!(cells.len(), dims.iter().product::<usize>());
+        let [cx, cy, cz] = dims.map(|d| d.div_ceil(CHUNK));
+        Self {
+            dims,
+            cells,
+            changed: vec![true; cx * cy * cz],
+            last_step: None,
+        }
+    }
+
+    /// Every cell, laid out as described on [`Grid`].
+    pub(crate) fn packed(&self) -> &[PackedCell] {
+        &self.cells
+    }
+
+    /// Width, height and depth.
+    pub fn dims(&self) -> [usize; 3] {
+        self.dims
     }
 
-    pub fn len(&self) -> usize {
-        self.0.len()
+    /// Total number of cells, that is the product of the [`Grid::dims`].
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
     }
 
     fn points(&self) -> impl Iterator<Item = Point> {
-        let l = self.len();
-        iproduct!(0..l, 0..l, 0..l).map(|(x, y, z)| point!(x, y, z))
+        let [w, h, d] = self.dims;
+        iproduct!(0..w, 0..h, 0..d).map(|(x, y, z)| point!(x, y, z))
+    }
+
+    fn index(&self, p: &Point) -> Option<usize> {
+        let [w, h, d] = self.dims;
+        let (x, y, z) = (p.0[Dim::X], p.0[Dim::Y], p.0[Dim::Z]);
+        (x < w && y < h && z < d).then(|| (x * h + y) * d + z)
     }
 
-    fn get(&self, p: &Point) -> Option<&CellStatus> {
-        self.0.get(p.0[Dim::X])?.get(p.0[Dim::Y])?.get(p.0[Dim::Z])
+    pub fn get(&self, p: &Point) -> Option<CellStatus> {
+        self.index(p).map(|i| self.cells[i].into())
+    }
+
+    /// # Panics
+    ///
+    /// If `p` lies outside the grid.
+    pub fn set(&mut self, p: &Point, c: CellStatus) {
+        let i = self.index(p).expect("point out of bounds");
+        self.cells[i] = c.into();
+        let [_, ny, nz] = self.chunk_counts();
+        let [x, y, z] = <[usize; 3]>::from(p.clone()).map(|c| c / CHUNK);
+        self.changed[(x * ny + y) * nz + z] = true;
+    }
+}
+
+/// Single-byte encoding of a [`CellStatus`].
+///
+/// `0` is dead, `u8::MAX` is alive and anything in between is the health of a
+/// dying cell. Rules can't have more than 255 states, so a dying cell's health
+/// never reaches `u8::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PackedCell(pub(crate) u8);
+
+impl PackedCell {
+    pub(crate) const DEAD: Self = Self(0);
+    pub(crate) const ALIVE: Self = Self(u8::MAX);
+}
+
+impl From<CellStatus> for PackedCell {
+    fn from(value: CellStatus) -> Self {
+        match value {
+            CellStatus::Dead => Self::DEAD,
+            CellStatus::Alive => Self::ALIVE,
+            CellStatus::Dying { health } => Self(health),
+        }
     }
+}
 
-    fn get_mut(&mut self, p: &Point) -> Option<&mut CellStatus> {
-        self.0
-            .get_mut(p.0[Dim::X])?
-            .get_mut(p.0[Dim::Y])?
-            .get_mut(p.0[Dim::Z])
+impl From<PackedCell> for CellStatus {
+    fn from(value: PackedCell) -> Self {
+        match value {
+            PackedCell::DEAD => Self::Dead,
+            PackedCell::ALIVE => Self::Alive,
+            PackedCell(health) => Self::Dying { health },
+        }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Point(EnumMap<Dim, usize>);
 
+#[cfg(feature = "viewer")]
 impl From<Point> for Vec3 {
     fn from(value: Point) -> Self {
         Self::new(
@@ -122,30 +518,30 @@ impl From<Point> for Vec3 {
     }
 }
 
+impl From<Point> for [usize; 3] {
+    fn from(value: Point) -> Self {
+        [value.0[Dim::X], value.0[Dim::Y], value.0[Dim::Z]]
+    }
+}
+
 impl Point {
-    fn dist(&self, other: &Self) -> f32 {
-        Vec3::from(self.clone()).distance(Vec3::from(other.clone()))
-    }
-    fn neighbors(&self, n: &Neighbors) -> Vec<Self> {
-        match n {
-            Neighbors::Moore => iproduct!(-1isize..=1, -1isize..=1, -1isize..=1)
-                .filter(|o| *o != (0, 0, 0))
-                .zip(iter::repeat(self.clone()))
-                .map(|((x, y, z), mut p)| {
-                    Dim::iter()
-                        .zip([x, y, z])
-                        .for_each(|(d, o)| p.0[d] = p.0[d].wrapping_add_signed(o));
-                    p
-                })
-                .collect(),
-            Neighbors::Neumann => iproduct!(Dim::iter(), [-1, 1])
-                .zip(iter::repeat(self.clone()))
-                .map(|((d, o), mut p)| {
-                    p.0[d] = p.0[d].wrapping_add_signed(o);
-                    p
-                })
-                .collect(),
-        }
+    pub fn new(x: usize, y: usize, z: usize) -> Self {
+        point!(x, y, z)
+    }
+
+    /// The points around this one in neighborhood `n`, in the order of
+    /// [`Neighbors::offsets`]. Coordinates wrap around at the ends of `usize`.
+    pub fn neighbors(&self, n: &Neighbors) -> Vec<Self> {
+        n.offsets()
+            .into_iter()
+            .map(|o| {
+                let mut p = self.clone();
+                Dim::iter()
+                    .zip(o)
+                    .for_each(|(d, o)| p.0[d] = p.0[d].wrapping_add_signed(o as isize));
+                p
+            })
+            .collect()
     }
 }
 
@@ -160,11 +556,67 @@ enum Dim {
 mod tests {
     use super::*;
 
+    impl Grid {
+        /// Counts live neighbors by visiting every offset. [`NeighborCounts`]
+        /// must always agree with this.
+        fn count_neighbors(&self, p: &Point, offsets: &[[i8; 3]], boundary: Boundary) -> usize {
+            offsets
+                .iter()
+                .filter(|o| {
+                    let mut n = p.clone();
+                    for (d, o) in Dim::iter().zip(*o) {
+                        match boundary.resolve(p.0[d] as isize + *o as isize, self.dims[d as usize])
+                        {
+                            Some(c) => n.0[d] = c,
+                            None => return boundary == Boundary::AliveWall,
+                        }
+                    }
+                    self.get(&n) == Some(CellStatus::Alive)
+                })
+                .count()
+        }
+    }
+
+    #[test]
+    fn packed_cell_round_trip() {
+        for c in [
+            CellStatus::Dead,
+            CellStatus::Alive,
+            CellStatus::Dying { health: 1 },
+            CellStatus::Dying { health: 253 },
+        ] {
+            assert_eq!(CellStatus::from(PackedCell::from(c)), c);
+        }
+    }
+
+    #[test]
+    fn grid_indexing() {
+        let mut g = Grid::new([3, 4, 2]);
+        assert_eq!(g.cell_count(), 24);
+        g.set(&point!(1, 2, 0), CellStatus::Alive);
+        g.set(&point!(2, 0, 1), CellStatus::Dying { health: 2 });
+        g.set(&point!(0, 3, 1), CellStatus::Alive);
+        g.set(&point!(0, 3, 1), CellStatus::Dead);
+        assert_eq!(g.get(&point!(1, 2, 0)), Some(CellStatus::Alive));
+        assert_eq!(g.get(&point!(0, 3, 1)), Some(CellStatus::Dead));
+        assert_eq!(g.get(&point!(3, 0, 0)), None);
+        assert_eq!(g.get(&point!(0, 0, 2)), None);
+        assert_eq!(g.get(&point!(0, usize::MAX, 0)), None);
+        let live = g.iter().filter(|(_, c)| c.is_live()).collect::<Vec<_>>();
+        assert_eq!(
+            live,
+            vec![
+                (point!(1, 2, 0), CellStatus::Alive),
+                (point!(2, 0, 1), CellStatus::Dying { health: 2 }),
+            ]
+        );
+    }
+
     #[test]
     fn neighbors_neumann() {
         let p = point!(1, 1, 1);
         assert_eq!(
-            p.neighbors(&Neighbors::Neumann),
+            p.neighbors(&Neighbors::Neumann(1)),
             vec![
                 point!(0, 1, 1),
                 point!(2, 1, 1),
@@ -179,7 +631,7 @@ mod tests {
     #[test]
     fn neighbors_moore() {
         let p = point!(1, 1, 1);
-        let ne = p.neighbors(&Neighbors::Moore);
+        let ne = p.neighbors(&Neighbors::Moore(1));
         assert_eq!(ne.len(), 26);
         assert_eq!(
             ne,
@@ -219,7 +671,7 @@ mod tests {
     fn neighbors_wrapping() {
         let p = point!(0, usize::MAX, 0);
         assert_eq!(
-            p.neighbors(&Neighbors::Neumann),
+            p.neighbors(&Neighbors::Neumann(1)),
             vec![
                 point!(usize::MAX, usize::MAX, 0),
                 point!(1, usize::MAX, 0),
@@ -230,4 +682,236 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn boundary_corners() {
+        let l = 4;
+        let offsets = Neighbors::Moore(1).offsets();
+        let corners =
+            iproduct!([0, l - 1], [0, l - 1], [0, l - 1]).map(|(x, y, z)| point!(x, y, z));
+        for corner in corners {
+            let opposite = {
+                let mut p = corner.clone();
+                Dim::iter().for_each(|d| p.0[d] = l - 1 - p.0[d]);
+                p
+            };
+            let count = |alive: &Point, boundary| {
+                let mut g = Grid::new([l; 3]);
+                g.set(alive, CellStatus::Alive);
+                g.count_neighbors(&corner, &offsets, boundary)
+            };
+            // only the corner itself is alive: only its reflections are seen
+            assert_eq!(count(&corner, Boundary::DeadWall), 0);
+            assert_eq!(count(&corner, Boundary::AliveWall), 19);
+            assert_eq!(count(&corner, Boundary::Torus), 0);
+            assert_eq!(count(&corner, Boundary::Mirror), 7);
+            // only the opposite corner is alive: only the torus wraps around to it
+            assert_eq!(count(&opposite, Boundary::DeadWall), 0);
+            assert_eq!(count(&opposite, Boundary::AliveWall), 19);
+            assert_eq!(count(&opposite, Boundary::Torus), 1);
+            assert_eq!(count(&opposite, Boundary::Mirror), 0);
+        }
+    }
+
+    #[test]
+    fn neighbor_counts_match_enumeration() {
+        let g = Grid::new_noise(
+            [9, 5, 12],
+            &NoiseSettings {
+                seed: 7,
+                threshold: 0.,
+                size: 9,
+                ..Default::default()
+            },
+        );
+        for (neighbors, boundary) in iproduct!(
+            [
+                Neighbors::Moore(1),
+                Neighbors::Moore(2),
+                Neighbors::Moore(5),
+                Neighbors::Neumann(1),
+                Neighbors::Neumann(3),
+                Neighbors::Neumann(6),
+                Neighbors::face_edge(),
+                Neighbors::corners(),
+                Neighbors::custom([[0, 0, 2], [0, 0, 3], [-2, 1, 0], [1, 1, 1], [0, 0, -1]]),
+            ],
+            Boundary::iter()
+        ) {
+            let offsets = neighbors.offsets();
+            let shape = Shape::new(&neighbors);
+            let counts = g.neighbor_counts(&shape, boundary, [0..9, 0..5, 0..12]);
+            for p in g.points() {
+                let (x, y, z) = (p.0[Dim::X], p.0[Dim::Y], p.0[Dim::Z]);
+                assert_eq!(
+                    counts.get(x, y, z),
+                    g.count_neighbors(&p, &offsets, boundary),
+                    "{neighbors:?} {boundary:?} at {p:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn neighborhood_sizes() {
+        let g = Grid::new([1; 3]);
+        let counts = |neighbors| {
+            let shape = Shape::new(&neighbors);
+            g.neighbor_counts(&shape, Boundary::AliveWall, [0..1, 0..1, 0..1])
+                .get(0, 0, 0)
+        };
+        assert_eq!(counts(Neighbors::Moore(1)), 26);
+        assert_eq!(counts(Neighbors::Moore(5)), 1330);
+        assert_eq!(counts(Neighbors::Neumann(1)), 6);
+        assert_eq!(counts(Neighbors::Neumann(2)), 24);
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let rules = [
+            "4/4/5/M",
+            "9-26/5-7,12-13,15/5/M",
+            "0-6/1,3/2/N2",
+            "2-5/3/7/C(0,0,1)(1,1,0)(-1,0,-1)",
+        ];
+        for (rule, seed) in iproduct!(rules, [1, 2, 3]) {
+            let rule = rule.parse::<Rule>().unwrap();
+            let noise = NoiseSettings {
+                seed,
+                threshold: 0.,
+                size: 8,
+                ..Default::default()
+            };
+            for boundary in Boundary::iter() {
+                let mut serial = Grid::new_noise([13, 20, 9], &noise);
+                let mut parallel = serial.clone();
+                for _ in 0..4 {
+                    serial = serial.next_serial(&rule, boundary);
+                    parallel = parallel.next(&rule, boundary);
+                    assert!(
+                        serial.cells == parallel.cells,
+                        "{rule:?} {seed} {boundary:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn active_chunks_match_full_recomputation() {
+        let rules = ["4/4/5/M", "2-6/3/3/N2", "/2-3/4/C(0,0,5)(1,0,0)"];
+        let noise = NoiseSettings {
+            seed: 3,
+            threshold: 0.,
+            size: 6,
+            ..Default::default()
+        };
+        for (rule, boundary) in iproduct!(rules, Boundary::iter()) {
+            let rule = rule.parse::<Rule>().unwrap();
+            let mut tracked = Grid::new_noise([37, 29, 45], &noise);
+            let mut full = tracked.clone();
+            for _ in 0..12 {
+                tracked = tracked.next(&rule, boundary);
+                full.last_step = None;
+                full = full.next_serial(&rule, boundary);
+                assert!(tracked.cells == full.cells, "{rule:?} {boundary:?}");
+            }
+            if boundary == Boundary::DeadWall && rule.neighbors == Neighbors::Moore(1) {
+                // the pattern stays near the center, so the corners go quiet
+                assert!(!tracked.changed[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn noise_core_shapes() {
+        // every cell in the core is alive, as the noise never drops below -1
+        let count = |dims, shape, size| {
+            let n = NoiseSettings {
+                threshold: -2.,
+                size,
+                shape,
+                ..Default::default()
+            };
+            Grid::new_noise(dims, &n)
+                .iter()
+                .filter(|(_, c)| *c == CellStatus::Alive)
+                .count()
+        };
+        assert_eq!(count([20, 20, 20], CoreShape::Cube, 3), 5 * 5 * 5);
+        assert_eq!(count([20, 20, 7], CoreShape::Full, 3), 20 * 20 * 7);
+        let disc = count([20, 20, 1], CoreShape::Sphere, 4);
+        assert_eq!(count([20, 20, 7], CoreShape::Cylinder, 4), disc * 7);
+        assert_eq!(
+            count([20, 20, 20], CoreShape::Ellipsoid([4; 3]), 9),
+            count([20, 20, 20], CoreShape::Sphere, 4)
+        );
+        assert_eq!(count([20, 20, 20], CoreShape::Ellipsoid([9, 1, 1]), 0), 17);
+
+        for shape in CoreShape::all(10) {
+            assert_eq!(shape.to_string().parse(), Ok(shape));
+        }
+        assert!("ellipsoid:1x0x1".parse::<CoreShape>().is_err());
+        assert!("blob".parse::<CoreShape>().is_err());
+    }
+
+    #[test]
+    fn noise_frequency_and_offset() {
+        let dims = [24, 24, 24];
+        let base = NoiseSettings {
+            threshold: 0.,
+            shape: CoreShape::Full,
+            ..Default::default()
+        };
+        // the default settings sample the noise at the cell coordinates
+        let noise = OpenSimplex::new(base.seed);
+        let g = Grid::new_noise(dims, &base);
+        for (p, c) in g.iter() {
+            let alive = noise.get(<[usize; 3]>::from(p).map(|c| c as f64)) > 0.;
+            assert_eq!(c == CellStatus::Alive, alive);
+        }
+
+        // moving the noise by one cell along x shifts the seed back a cell
+        let shifted = NoiseSettings {
+            offset: [1., 0., 0.],
+            ..base.clone()
+        };
+        let (a, b) = (
+            base.slice(dims, 5, Symmetry::None),
+            shifted.slice(dims, 5, Symmetry::None),
+        );
+        for (y, x) in iproduct!(0..24, 0..23) {
+            assert_eq!(b[y * 24 + x], a[y * 24 + x + 1]);
+        }
+
+        // doubling the frequency is the same as halving the grid, and the
+        // scale multiplies it per axis
+        let fast = NoiseSettings {
+            frequency: 2.,
+            ..base.clone()
+        };
+        let stretched = NoiseSettings {
+            scale: [2., 2., 1.],
+            ..base.clone()
+        };
+        let (a, b, c) = (
+            base.slice(dims, 10, Symmetry::None),
+            fast.slice(dims, 5, Symmetry::None),
+            stretched.slice(dims, 10, Symmetry::None),
+        );
+        for (y, x) in iproduct!(0..12, 0..12) {
+            assert_eq!(b[y * 24 + x], a[2 * y * 24 + 2 * x]);
+            assert_eq!(c[y * 24 + x], a[2 * y * 24 + 2 * x]);
+        }
+
+        // a slice is one layer of the grid
+        let layer = g
+            .iter()
+            .filter(|(p, _)| <[usize; 3]>::from(p.clone())[2] == 7)
+            .map(|(p, c)| (<[usize; 3]>::from(p), c == CellStatus::Alive));
+        let slice = base.slice(dims, 7, Symmetry::None);
+        for ([x, y, _], alive) in layer {
+            assert_eq!(slice[y * 24 + x], alive);
+        }
+    }
 }
@@ -0,0 +1,269 @@
+//! Runs a simulation without opening a window, writing per-generation
+//! statistics and optional snapshots of the grid.
+
+use crate::{
+    automaton::Automaton,
+    cycle::CycleDetector,
+    grid::{parse_dims, parse_triple, Boundary, Grid, NoiseSettings},
+    preset::Preset,
+    rule::Rule,
+    snapshot::{Snapshot, MAX_CELLS},
+    sparse::SparseGrid,
+    stats::Stats,
+    symmetry::Symmetry,
+};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    time::Instant,
+};
+use strum::IntoEnumIterator;
+
+pub const USAGE: &str = "\
+usage: headless [options]
+
+options:
+  --preset <name>        start from a built-in preset; later options override it
+  --rule <rule>          rule string, e.g. 4/4/5/M (default: 4/4/5/M)
+  --seed <n>             noise seed (default: 1)
+  --threshold <x>        noise threshold (default: 0.1)
+  --core-size <n>        radius of the seeded core (default: 10)
+  --core-shape <shape>   sphere, cube, cylinder, full or ellipsoid:XxYxZ (default: sphere)
+  --frequency <x>        noise frequency (default: 1)
+  --offset <x,y,z>       move the noise by this much (default: 0,0,0)
+  --scale <x,y,z>        multiply the frequency along each axis (default: 1,1,1)
+  --symmetry <name>      copy the seed around the middle: none, mirror-x, mirror-xy,
+                         mirror-xyz, rotate-4, rotate-8 or octahedral (default: none)
+  --size <n|WxHxD>       grid side length, or width, height and depth (default: 50)
+  --generations <n>      generations to run (default: 100)
+  --boundary <name>      dead-wall, alive-wall, torus or mirror (default: dead-wall)
+  --stop-on-cycle        stop once the run settles into a still life or oscillator
+  --unbounded            grow without edges, seeding a --size box (ignores --boundary)
+  --out <dir>            write stats.csv (and snapshots) here instead of stdout
+  --snapshot-every <n>   with --out, write a grid snapshot every n generations";
+
+struct Options {
+    rule: Rule,
+    noise: NoiseSettings,
+    symmetry: Symmetry,
+    dims: [usize; 3],
+    generations: usize,
+    boundary: Boundary,
+    unbounded: bool,
+    stop_on_cycle: bool,
+    out: Option<PathBuf>,
+    snapshot_every: Option<usize>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut opts = Self {
+            rule: "4/4/5/M".parse().unwrap(),
+            noise: NoiseSettings::default(),
+            symmetry: Symmetry::None,
+            dims: [50; 3],
+            generations: 100,
+            boundary: Boundary::default(),
+            unbounded: false,
+            stop_on_cycle: false,
+            out: None,
+            snapshot_every: None,
+        };
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {flag}"));
+            match flag.as_str() {
+                "--preset" => {
+                    let name = value()?;
+                    let preset = Preset::builtin()
+                        .into_iter()
+                        .find(|p| p.name.eq_ignore_ascii_case(name))
+                        .ok_or(format!("unknown preset {name}"))?;
+                    opts.rule = preset.rule;
+                    opts.noise = preset.noise;
+                    opts.dims = preset.dims;
+                }
+                "--rule" => {
+                    opts.rule = value()?
+                        .parse()
+                        .map_err(|e| format!("invalid rule:\n{e}"))?
+                }
+                "--seed" => opts.noise.seed = parse_num(flag, value()?)?,
+                "--threshold" => opts.noise.threshold = parse_num(flag, value()?)?,
+                "--core-size" => opts.noise.size = parse_num(flag, value()?)?,
+                "--core-shape" => opts.noise.shape = value()?.parse()?,
+                "--frequency" => opts.noise.frequency = parse_num(flag, value()?)?,
+                "--offset" | "--scale" => {
+                    let value = value()?;
+                    let triple =
+                        parse_triple(value).ok_or(format!("invalid value for {flag}: {value}"))?;
+                    if flag == "--offset" {
+                        opts.noise.offset = triple;
+                    } else {
+                        opts.noise.scale = triple;
+                    }
+                }
+                "--symmetry" => opts.symmetry = value()?.parse()?,
+                "--size" => {
+                    let value = value()?;
+                    opts.dims =
+                        parse_dims(value).ok_or(format!("invalid value for --size: {value}"))?
+                }
+                "--generations" => opts.generations = parse_num(flag, value()?)?,
+                "--boundary" => {
+                    let name = value()?;
+                    opts.boundary = Boundary::iter()
+                        .find(|b| b.to_string().to_lowercase().replace(' ', "-") == *name)
+                        .ok_or(format!("unknown boundary {name}"))?
+                }
+                "--unbounded" => opts.unbounded = true,
+                "--stop-on-cycle" => opts.stop_on_cycle = true,
+                "--out" => opts.out = Some(value()?.into()),
+                "--snapshot-every" => opts.snapshot_every = Some(parse_num(flag, value()?)?),
+                _ => return Err(format!("unknown option {flag}")),
+            }
+        }
+        if opts.snapshot_every.is_some() && opts.out.is_none() {
+            return Err("--snapshot-every requires --out".into());
+        }
+        if opts.snapshot_every == Some(0) {
+            return Err("--snapshot-every must be at least 1".into());
+        }
+        let fits = opts
+            .dims
+            .iter()
+            .try_fold(1usize, |n, d| n.checked_mul(*d))
+            .is_some_and(|n| n <= MAX_CELLS);
+        if opts.snapshot_every.is_some() && !opts.unbounded && !fits {
+            return Err(format!("snapshots hold at most {MAX_CELLS} cells"));
+        }
+        Ok(opts)
+    }
+}
+
+fn parse_num<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {flag}: {value}"))
+}
+
+/// Parses `args` (everything after the program name) and runs the simulation.
+pub fn run(args: &[String]) -> Result<(), String> {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    let opts = Options::parse(args)?;
+    if opts.unbounded {
+        simulate::<SparseGrid>(&opts)
+    } else {
+        simulate::<Grid>(&opts)
+    }
+    .map_err(|e| e.to_string())
+}
+
+fn simulate<G: Automaton>(opts: &Options) -> io::Result<()> {
+    let mut csv: Box<dyn Write> = match &opts.out {
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            Box::new(BufWriter::new(File::create(dir.join("stats.csv"))?))
+        }
+        None => Box::new(io::stdout().lock()),
+    };
+    Stats::write_csv_header(&mut csv, opts.rule.states)?;
+
+    let start = Instant::now();
+    let mut g = G::from_grid(&Grid::new_noise(opts.dims, &opts.noise).symmetrized(opts.symmetry));
+    let mut previous = None;
+    let mut cycles = CycleDetector::new(1000);
+    let mut generations = 0;
+    for generation in 0..=opts.generations {
+        if generation > 0 {
+            let next = g.next(&opts.rule, opts.boundary);
+            previous = Some(std::mem::replace(&mut g, next));
+        }
+        generations = generation;
+        let stats = Stats::new(&g, previous.as_ref(), opts.rule.states);
+        stats.write_csv_row(&mut csv, generation as u64)?;
+        let was_cycling = cycles.cycle().is_some();
+        if let Some(cycle) = cycles.push(generation as u64, stats.fingerprint) {
+            if !was_cycling {
+                eprintln!("generation {generation}: {cycle}");
+            }
+        }
+        if let (Some(dir), Some(every)) = (&opts.out, opts.snapshot_every) {
+            if generation % every == 0 {
+                let snapshot = Snapshot {
+                    grid: g.to_grid(),
+                    rule: opts.rule.clone(),
+                    generation: generation as u64,
+                };
+                let path = dir.join(format!("snapshot_{generation:06}.ca3d"));
+                let mut f = BufWriter::new(File::create(path)?);
+                snapshot.write(&mut f)?;
+                f.flush()?;
+            }
+        }
+        if opts.stop_on_cycle && cycles.cycle().is_some() {
+            break;
+        }
+    }
+    csv.flush()?;
+    eprintln!(
+        "ran {} generations of a {} {} in {:.2?}",
+        generations,
+        opts.dims.map(|d| d.to_string()).join("×"),
+        if opts.unbounded { "seed" } else { "grid" },
+        start.elapsed()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Neighbors;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parse_options() {
+        let opts = Options::parse(&args(
+            "--rule /5-7/5/N --size 20 --generations 3 --boundary torus --seed 4",
+        ))
+        .unwrap();
+        assert_eq!(opts.rule.neighbors, Neighbors::Neumann(1));
+        assert_eq!(opts.dims, [20; 3]);
+        assert_eq!(
+            Options::parse(&args("--size 200x200x8")).unwrap().dims,
+            [200, 200, 8]
+        );
+        assert_eq!(opts.generations, 3);
+        assert_eq!(opts.boundary, Boundary::Torus);
+        assert_eq!(opts.noise.seed, 4);
+        assert_eq!(opts.noise.threshold, NoiseSettings::default().threshold);
+        assert!(!opts.unbounded);
+        assert!(Options::parse(&args("--unbounded")).unwrap().unbounded);
+        assert!(
+            Options::parse(&args("--stop-on-cycle"))
+                .unwrap()
+                .stop_on_cycle
+        );
+        let clouds = Options::parse(&args("--preset clouds --seed 9")).unwrap();
+        assert_eq!(clouds.rule, "13-26/13-14,17-19/2/M".parse().unwrap());
+        assert_eq!(clouds.noise.seed, 9);
+
+        assert!(Options::parse(&args("--size")).is_err());
+        assert!(Options::parse(&args("--size many")).is_err());
+        assert!(Options::parse(&args("--size 4x4")).is_err());
+        assert!(Options::parse(&args("--boundary sphere")).is_err());
+        assert!(Options::parse(&args("--preset nonesuch")).is_err());
+        assert!(Options::parse(&args("--snapshot-every 5")).is_err());
+        assert!(Options::parse(&args("--out runs --snapshot-every 0")).is_err());
+        assert!(Options::parse(&args("--out runs --snapshot-every 5 --size 500")).is_err());
+        assert!(Options::parse(&args("--frobnicate 1")).is_err());
+    }
+}
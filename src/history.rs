@@ -0,0 +1,221 @@
+//! A bounded record of past generations of a [`Grid`], kept as the changes
+//! from each generation to the next.
+
+use crate::grid::{Grid, PackedCell};
+use std::{collections::VecDeque, mem::size_of};
+
+/// The cells that differ between one generation and the next.
+struct Delta {
+    indices: Vec<u32>,
+    cells: Vec<PackedCell>,
+}
+
+impl Delta {
+    fn between(old: &[PackedCell], new: &[PackedCell]) -> Self {
+        let (indices, cells) = old
+            .iter()
+            .zip(new)
+            .enumerate()
+            .filter(|(_, (o, n))| o != n)
+            .map(|(i, (_, n))| (i as u32, *n))
+            .unzip();
+        Self { indices, cells }
+    }
+
+    fn apply(&self, cells: &mut [PackedCell]) {
+        for (i, c) in self.indices.iter().zip(&self.cells) {
+            cells[*i as usize] = *c;
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        self.indices.len() * (size_of::<u32>() + size_of::<PackedCell>())
+    }
+}
+
+/// Past generations of one run of a [`Grid`], up to a fixed number of them.
+///
+/// Only the oldest generation is stored in full; every later one is the
+/// changes from the one before, so slowly changing grids take little memory.
+/// Once full, the oldest generation is dropped for each new one.
+pub struct History {
+    dims: [usize; 3],
+    /// Generation number of `base`.
+    first: u64,
+    /// Cells of the oldest generation kept.
+    base: Vec<PackedCell>,
+    /// Changes from each generation to the next, oldest first.
+    deltas: VecDeque<Delta>,
+    /// Cells of the newest generation, which the next one is compared to.
+    latest: Vec<PackedCell>,
+    capacity: usize,
+}
+
+impl History {
+    /// Starts a history at `grid`, which is generation `generation`, keeping
+    /// at most `capacity` generations.
+    pub fn new(grid: &Grid, generation: u64, capacity: usize) -> Self {
+        assert!(
+            grid.cell_count() <= u32::MAX as usize,
+            "grid too large for a history"
+        );
+        Self {
+            dims: grid.dims(),
+            first: generation,
+            base: grid.packed().to_vec(),
+            deltas: VecDeque::new(),
+            latest: grid.packed().to_vec(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Records `grid` as the generation after [`History::latest`].
+    ///
+    /// # Panics
+    ///
+    /// If `grid` doesn't have the dimensions of the grids already recorded.
+    pub fn push(&mut self, grid: &Grid) {
+        assert_eq!(grid.dims(), self.dims, "grid dimensions changed");
+        self.deltas
+            .push_back(Delta::between(&self.latest, grid.packed()));
+        self.latest.copy_from_slice(grid.packed());
+        self.evict();
+    }
+
+    /// The oldest generation kept.
+    pub fn first(&self) -> u64 {
+        self.first
+    }
+
+    /// The newest generation kept.
+    pub fn latest(&self) -> u64 {
+        self.first + self.deltas.len() as u64
+    }
+
+    /// Rebuilds the grid as it was at `generation`, if it's still kept.
+    pub fn get(&self, generation: u64) -> Option<Grid> {
+        self.cells_at(generation)
+            .map(|cells| Grid::from_packed(self.dims, cells))
+    }
+
+    /// Forgets every generation after `generation`, so the run can continue
+    /// differently from there.
+    pub fn truncate(&mut self, generation: u64) {
+        if let Some(cells) = self.cells_at(generation) {
+            self.deltas.truncate((generation - self.first) as usize);
+            self.latest = cells;
+        }
+    }
+
+    /// Changes how many generations are kept, dropping the oldest ones if
+    /// there are now too many.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        self.evict();
+    }
+
+    /// Roughly how many bytes the recorded generations take up.
+    pub fn memory(&self) -> usize {
+        (self.base.len() + self.latest.len()) * size_of::<PackedCell>()
+            + self.deltas.iter().map(Delta::bytes).sum::<usize>()
+    }
+
+    fn cells_at(&self, generation: u64) -> Option<Vec<PackedCell>> {
+        if !(self.first()..=self.latest()).contains(&generation) {
+            return None;
+        }
+        if generation == self.latest() {
+            return Some(self.latest.clone());
+        }
+        let mut cells = self.base.clone();
+        for d in self.deltas.iter().take((generation - self.first) as usize) {
+            d.apply(&mut cells);
+        }
+        Some(cells)
+    }
+
+    fn evict(&mut self) {
+        while self.deltas.len() >= self.capacity {
+            let Some(oldest) = self.deltas.pop_front() else {
+                break;
+            };
+            oldest.apply(&mut self.base);
+            self.first += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::{Boundary, NoiseSettings},
+        rule::Rule,
+    };
+
+    fn run(rule: &str, dims: [usize; 3], generations: usize) -> Vec<Grid> {
+        let rule: Rule = rule.parse().unwrap();
+        let mut grids = vec![Grid::new_noise(dims, &NoiseSettings::default())];
+        for _ in 0..generations {
+            let next = grids.last().unwrap().next(&rule, Boundary::Torus);
+            grids.push(next);
+        }
+        grids
+    }
+
+    fn same(a: &Grid, b: &Grid) -> bool {
+        a.dims() == b.dims() && a.packed() == b.packed()
+    }
+
+    #[test]
+    fn rebuilds_every_generation() {
+        let grids = run("4/4/5/M", [40; 3], 30);
+        let mut history = History::new(&grids[0], 0, 100);
+        for g in &grids[1..] {
+            history.push(g);
+        }
+        assert_eq!((history.first(), history.latest()), (0, 30));
+        for (i, g) in grids.iter().enumerate() {
+            assert!(same(&history.get(i as u64).unwrap(), g), "generation {i}");
+        }
+        assert!(history.get(31).is_none());
+        // far less than a full copy of every generation, as only the cells
+        // around the seeded core change
+        assert!(history.memory() < grids.len() * grids[0].cell_count() / 2);
+    }
+
+    #[test]
+    fn drops_the_oldest_generations() {
+        let grids = run("4/4/5/M", [20, 16, 12], 20);
+        let mut history = History::new(&grids[0], 100, 8);
+        for g in &grids[1..] {
+            history.push(g);
+        }
+        assert_eq!((history.first(), history.latest()), (113, 120));
+        assert!(history.get(112).is_none());
+        assert!(same(&history.get(113).unwrap(), &grids[13]));
+        assert!(same(&history.get(120).unwrap(), &grids[20]));
+
+        history.set_capacity(3);
+        assert_eq!((history.first(), history.latest()), (118, 120));
+        assert!(same(&history.get(118).unwrap(), &grids[18]));
+    }
+
+    #[test]
+    fn continues_from_a_past_generation() {
+        let grids = run("4/4/5/M", [20, 16, 12], 10);
+        let other = run("5-7/6/2/M", [20, 16, 12], 10);
+        let mut history = History::new(&grids[0], 0, 100);
+        for g in &grids[1..] {
+            history.push(g);
+        }
+        // resume from generation 4 with a different rule
+        history.truncate(4);
+        assert_eq!(history.latest(), 4);
+        let fork = other[5].clone();
+        history.push(&fork);
+        assert_eq!(history.latest(), 5);
+        assert!(same(&history.get(4).unwrap(), &grids[4]));
+        assert!(same(&history.get(5).unwrap(), &fork));
+    }
+}
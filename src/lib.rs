@@ -0,0 +1,46 @@
+//! 3D cellular automata.
+//!
+//! The simulation itself ([`Grid`], [`SparseGrid`], [`Rule`], [`CellStatus`])
+//! has no rendering dependency. With the default `viewer` feature, [`AutomataPlugin`] adds the
+//! simulation, an instanced renderer and a settings window to a Bevy app.
+
+#![allow(clippy::single_range_in_vec_init)]
+
+pub mod automaton;
+pub mod cell;
+pub mod cycle;
+pub mod grid;
+pub mod headless;
+pub mod history;
+mod neighborhood;
+pub mod preset;
+pub mod rule;
+pub mod seed;
+pub mod snapshot;
+pub mod sparse;
+pub mod stats;
+pub mod symmetry;
+
+#[cfg(feature = "viewer")]
+mod camera;
+#[cfg(feature = "viewer")]
+mod plugin;
+#[cfg(feature = "viewer")]
+mod rendering;
+#[cfg(feature = "viewer")]
+mod ui;
+
+pub use automaton::Automaton;
+pub use cell::CellStatus;
+pub use cycle::{Cycle, CycleDetector};
+pub use grid::{Boundary, CoreShape, Grid, NoiseSettings, Point};
+pub use history::History;
+#[cfg(feature = "viewer")]
+pub use plugin::{AutomataPlugin, GridReset, MainGrid, PlaybackControl};
+pub use preset::Preset;
+pub use rule::{Neighbors, Notation, ParseRuleError, Rule};
+pub use seed::{Blend, Generator, Layer, NoiseKind, Seed};
+pub use snapshot::{Snapshot, SnapshotError};
+pub use sparse::SparseGrid;
+pub use stats::Stats;
+pub use symmetry::Symmetry;
@@ -0,0 +1,137 @@
+//! Counting live neighbors in bulk, shared by [`Grid`](crate::Grid) and
+//! [`SparseGrid`](crate::SparseGrid).
+
+use crate::rule::Neighbors;
+use itertools::iproduct;
+use std::ops::RangeInclusive;
+
+/// A neighborhood, arranged for fast counting.
+pub(crate) enum Shape {
+    /// The full cube of the given radius, origin included.
+    Cube(usize),
+    /// Neighbors within the given radius, grouped into runs of consecutive z
+    /// offsets.
+    Runs(usize, Vec<Run>),
+}
+
+/// The neighbors at `(dx, dy, z)` for every `z` in `dz`.
+pub(crate) struct Run {
+    dx: isize,
+    dy: isize,
+    dz: RangeInclusive<isize>,
+}
+
+impl Shape {
+    pub(crate) fn new(neighbors: &Neighbors) -> Self {
+        let r = neighbors.radius() as usize;
+        if let Neighbors::Moore(_) = neighbors {
+            return Self::Cube(r);
+        }
+        // sorted, so offsets along the same z line are next to each other
+        let mut offsets = neighbors.offsets();
+        offsets.sort();
+        let mut runs = Vec::<Run>::new();
+        for [dx, dy, dz] in offsets.into_iter().map(|o| o.map(isize::from)) {
+            match runs.last_mut() {
+                Some(run) if (run.dx, run.dy) == (dx, dy) && *run.dz.end() + 1 == dz => {
+                    run.dz = *run.dz.start()..=dz;
+                }
+                _ => runs.push(Run {
+                    dx,
+                    dy,
+                    dz: dz..=dz,
+                }),
+            }
+        }
+        Self::Runs(r, runs)
+    }
+
+    pub(crate) fn radius(&self) -> usize {
+        match self {
+            Self::Cube(r) | Self::Runs(r, _) => *r,
+        }
+    }
+}
+
+/// Live-neighbor counts for every cell of a box-shaped region, computed
+/// without visiting each neighbor individually.
+///
+/// The live cells around the region are copied into a volume padded on every
+/// side by the neighborhood radius. Moore neighborhoods are then box queries
+/// against a summed-volume table, and every other neighborhood is split into
+/// runs along the z axis that are summed from running totals.
+pub(crate) struct NeighborCounts<'a> {
+    shape: &'a Shape,
+    /// Side lengths of the padded region plus the leading zero plane.
+    dims: [usize; 3],
+    sums: Vec<u32>,
+}
+
+impl<'a> NeighborCounts<'a> {
+    /// Counts neighbors for the cells `0..extent` on each axis. `alive` is
+    /// asked about every cell within the neighborhood radius of the region,
+    /// in the same coordinates, so it also sees negative ones.
+    pub(crate) fn new(
+        shape: &'a Shape,
+        extent: [usize; 3],
+        alive: impl Fn([isize; 3]) -> bool,
+    ) -> Self {
+        let r = shape.radius();
+        let dims = extent.map(|e| e + 2 * r + 1);
+        let [dx, dy, dz] = dims;
+        let idx = |x: usize, y: usize, z: usize| (x * dy + y) * dz + z;
+        let padded = |c: usize| c as isize - 1 - r as isize;
+
+        let mut sums = vec![0u32; dx * dy * dz];
+        for (x, y, z) in iproduct!(1..dx, 1..dy, 1..dz) {
+            let live = alive([padded(x), padded(y), padded(z)]);
+            sums[idx(x, y, z)] = live as u32 + sums[idx(x, y, z - 1)];
+        }
+        // for cubes, running sums along y and x as well
+        if let Shape::Cube(_) = shape {
+            for (x, y, z) in iproduct!(1..dx, 1..dy, 1..dz) {
+                sums[idx(x, y, z)] += sums[idx(x, y - 1, z)];
+            }
+            for (x, y, z) in iproduct!(1..dx, 1..dy, 1..dz) {
+                sums[idx(x, y, z)] += sums[idx(x - 1, y, z)];
+            }
+        }
+
+        Self { shape, dims, sums }
+    }
+
+    /// Number of live neighbors around the cell at `(x, y, z)` of the region.
+    pub(crate) fn get(&self, x: usize, y: usize, z: usize) -> usize {
+        let [_, dy, dz] = self.dims;
+        let s = |x: usize, y: usize, z: usize| self.sums[(x * dy + y) * dz + z] as i64;
+        let r = self.shape.radius();
+        // Sum of the padded cells `x + lo..x + hi` on every axis, which is
+        // `x + lo + 1..=x + hi` once the leading zero plane is accounted for.
+        let cube = |lo: usize, hi: usize, [x, y, z]: [usize; 3]| {
+            let ([x0, y0, z0], [x1, y1, z1]) = ([x + lo, y + lo, z + lo], [x + hi, y + hi, z + hi]);
+            s(x1, y1, z1) - s(x0, y1, z1) - s(x1, y0, z1) - s(x1, y1, z0)
+                + s(x0, y0, z1)
+                + s(x0, y1, z0)
+                + s(x1, y0, z0)
+                - s(x0, y0, z0)
+        };
+        match self.shape {
+            Shape::Cube(_) => {
+                // in padded coordinates the cube spans `x..=x + 2r` and the
+                // cell itself sits at `x + r`
+                let total = cube(0, 2 * r + 1, [x, y, z]);
+                let own = cube(r, r + 1, [x, y, z]);
+                (total - own) as usize
+            }
+            Shape::Runs(_, ref runs) => {
+                let at = |c: usize, o: isize| (c + r).wrapping_add_signed(o);
+                runs.iter()
+                    .map(|run| {
+                        let (px, py) = (at(x, run.dx) + 1, at(y, run.dy) + 1);
+                        s(px, py, at(z, *run.dz.end()) + 1) - s(px, py, at(z, *run.dz.start()))
+                    })
+                    .sum::<i64>() as usize
+            }
+        }
+    }
+}
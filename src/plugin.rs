@@ -0,0 +1,831 @@
+//! The [`AutomataPlugin`], which runs a simulation on the main grid and
+//! renders it with the instancing renderer and a settings window.
+
+use crate::{
+    automaton::{visible_cells, Automaton},
+    camera::{orbit_camera, spawn_camera, AutoRotate},
+    cycle::{Cycle, CycleDetector},
+    grid::{Boundary, Grid, NoiseSettings},
+    history::History,
+    rendering::*,
+    rule::{Neighbors, Rule},
+    seed::Seed,
+    snapshot::{Snapshot, SnapshotError},
+    sparse::SparseGrid,
+    stats::Stats,
+    symmetry::Symmetry,
+    ui::{draw_clipping, draw_playback, draw_stats, draw_window},
+};
+use bevy::{
+    prelude::*,
+    render::view::NoFrustumCulling,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+use bevy_egui::{EguiContexts, EguiPlugin};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::PathBuf,
+    time::Duration,
+};
+use strum::{Display, EnumIter};
+
+/// Adds the simulation, its instanced renderer and the settings window.
+pub struct AutomataPlugin;
+
+impl Plugin for AutomataPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Rule {
+            survival: vec![4..5],
+            birth: vec![4..5],
+            states: 5,
+            neighbors: Neighbors::Moore(1),
+        })
+        .init_resource::<NoiseSettings>()
+        .init_resource::<Seed>()
+        .init_resource::<Boundary>()
+        .init_resource::<GridSettings>()
+        .init_resource::<Generation>()
+        .init_resource::<SnapshotStatus>()
+        .init_resource::<Playback>()
+        .init_resource::<Timeline>()
+        .init_resource::<Population>()
+        .init_resource::<Cycles>()
+        .init_resource::<AutoRotate>()
+        .init_resource::<Clipping>()
+        .init_resource::<CullHidden>()
+        .insert_resource(GridTimer(Timer::new(
+            Playback::default().interval,
+            TimerMode::Repeating,
+        )))
+        .add_event::<GridReset>()
+        .add_event::<PlaybackControl>()
+        .add_event::<GridReplaced>()
+        .add_event::<SnapshotRequest>()
+        .add_plugins(CustomMaterialPlugin)
+        .add_systems(Startup, (create_grid, spawn_camera))
+        .add_systems(
+            Update,
+            (
+                playback_keys,
+                control_playback,
+                seek_history.before(update_grid::<Grid>),
+                reset_grid,
+                snapshot_io,
+                update_grid::<Grid>,
+                update_grid::<SparseGrid>,
+                start_population.after(reset_grid).after(snapshot_io),
+                detect_cycles
+                    .after(start_population)
+                    .after(update_grid::<Grid>)
+                    .after(update_grid::<SparseGrid>),
+                record_history
+                    .after(reset_grid)
+                    .after(snapshot_io)
+                    .after(seek_history)
+                    .after(update_grid::<Grid>),
+                render_grid_data::<Grid>,
+                render_grid_data::<SparseGrid>,
+                orbit_camera,
+            ),
+        )
+        .add_systems(
+            Update,
+            (draw_window, draw_playback, draw_stats, draw_clipping),
+        );
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+    }
+}
+
+#[derive(Resource)]
+struct GridTimer(Timer);
+
+/// The kind of grid a [`GridReset`] creates.
+#[derive(Resource)]
+pub(crate) struct GridSettings {
+    /// Width, height and depth of the grid, or of the seeded box for
+    /// unbounded grids.
+    pub(crate) dims: [usize; 3],
+    /// Whether to use a [`SparseGrid`] without edges instead of a [`Grid`].
+    pub(crate) unbounded: bool,
+    /// Whether to seed from the [`Seed`] layers instead of the
+    /// [`NoiseSettings`].
+    pub(crate) layered: bool,
+    /// Copied around the middle of the grid after seeding.
+    pub(crate) symmetry: Symmetry,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            dims: [50; 3],
+            unbounded: false,
+            layered: false,
+            symmetry: Symmetry::None,
+        }
+    }
+}
+
+impl GridSettings {
+    /// A freshly seeded grid of the configured size.
+    pub(crate) fn seed(&self, noise: &NoiseSettings, seed: &Seed) -> Grid {
+        let grid = if self.layered {
+            seed.generate(self.dims)
+        } else {
+            Grid::new_noise(self.dims, noise)
+        };
+        grid.symmetrized(self.symmetry)
+    }
+}
+
+/// How many generations the main grid has been stepped since it was seeded.
+#[derive(Resource, Default)]
+pub(crate) struct Generation(pub(crate) u64);
+
+/// Whether and how fast the main grid advances.
+#[derive(Resource)]
+pub(crate) struct Playback {
+    pub(crate) paused: bool,
+    /// Time between generations, unless running at [`Playback::max_speed`].
+    pub(crate) interval: Duration,
+    /// Step as soon as the next generation is ready instead of on a timer.
+    pub(crate) max_speed: bool,
+    /// Single steps requested while paused and not taken yet.
+    steps: u64,
+    /// Generations left before pausing again, when running a fixed number.
+    pub(crate) remaining: Option<u64>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            interval: Duration::from_millis(200),
+            max_speed: false,
+            steps: 0,
+            remaining: None,
+        }
+    }
+}
+
+/// Controls how the main grid advances. The settings window sends these, and
+/// they can equally be sent from key bindings or other systems.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum PlaybackControl {
+    Play,
+    Pause,
+    TogglePause,
+    /// Pauses, then advances exactly one generation.
+    Step,
+    /// Advances the given number of generations at the current speed, then
+    /// pauses.
+    Run(u64),
+    /// Pauses and shows the given generation from the [`Timeline`]. Playing
+    /// or stepping from there discards the generations after it.
+    Seek(u64),
+    /// Pauses and goes back one generation, if it's still in the timeline.
+    StepBack,
+    /// Sets the time between generations.
+    SetInterval(Duration),
+    /// Steps as fast as generations can be computed, ignoring the interval.
+    SetMaxSpeed(bool),
+}
+
+/// Past generations of the main grid, for rewinding. Unbounded grids have
+/// none.
+#[derive(Resource)]
+pub(crate) struct Timeline {
+    pub(crate) history: Option<History>,
+    /// How many generations the history keeps.
+    pub(crate) capacity: usize,
+    /// The generation of the grid last recorded or rewound to.
+    shown: u64,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            history: None,
+            capacity: 500,
+            shown: 0,
+        }
+    }
+}
+
+/// The [`Stats`] of each generation of the current run, oldest first.
+#[derive(Resource, Default)]
+pub(crate) struct Population(pub(crate) Vec<(u64, Stats)>);
+
+impl Population {
+    /// Adds the stats of `generation`, replacing those of it and any later
+    /// generations left over from before a rewind.
+    fn record(&mut self, generation: u64, stats: Stats) {
+        let keep = self.0.partition_point(|(g, _)| *g < generation);
+        self.0.truncate(keep);
+        self.0.push((generation, stats));
+    }
+}
+
+/// Which cells of the main grid are drawn.
+#[derive(Default, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+pub(crate) enum ClipMode {
+    /// Every cell.
+    #[default]
+    Off,
+    /// The cells between the clipping planes.
+    Planes,
+    /// One layer of cells across an axis.
+    #[strum(serialize = "Single slice")]
+    Slice,
+}
+
+/// Limits which cells are drawn, to see inside dense patterns. Positions are
+/// in cells from the middle of the grid as drawn.
+#[derive(Resource)]
+pub(crate) struct Clipping {
+    pub(crate) mode: ClipMode,
+    /// The lowest position drawn along each axis, in [`ClipMode::Planes`].
+    pub(crate) min: Vec3,
+    /// The highest position drawn along each axis, in [`ClipMode::Planes`].
+    pub(crate) max: Vec3,
+    /// A plane at any angle, also applied in [`ClipMode::Planes`], which
+    /// hides the cells in front of it.
+    pub(crate) cut: Option<ClipPlane>,
+    /// The axis across which [`ClipMode::Slice`] shows a layer.
+    pub(crate) slice_axis: usize,
+    /// Position of the layer along `slice_axis`.
+    pub(crate) slice_at: f32,
+}
+
+impl Default for Clipping {
+    fn default() -> Self {
+        Self {
+            mode: ClipMode::Off,
+            min: Vec3::splat(-f32::INFINITY),
+            max: Vec3::splat(f32::INFINITY),
+            cut: None,
+            slice_axis: 2,
+            slice_at: 0.,
+        }
+    }
+}
+
+/// Whether cells completely surrounded by drawn cells are left out of the
+/// drawing, which draws far fewer cubes for solid patterns. Off by default:
+/// cells are drawn smaller than the grid spacing, so the cells inside show
+/// through the gaps and leaving them out changes the picture.
+#[derive(Resource, Default)]
+pub(crate) struct CullHidden(pub(crate) bool);
+
+/// A plane through the grid facing any direction.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct ClipPlane {
+    /// Direction the plane faces, around the vertical axis, in radians.
+    pub(crate) azimuth: f32,
+    /// Direction the plane faces, above the horizontal, in radians.
+    pub(crate) elevation: f32,
+    /// Distance of the plane from the middle along the direction it faces.
+    pub(crate) offset: f32,
+}
+
+impl ClipPlane {
+    fn normal(&self) -> Vec3 {
+        Quat::from_euler(EulerRot::YXZ, self.azimuth, -self.elevation, 0.) * Vec3::Z
+    }
+}
+
+impl Clipping {
+    /// Whether to draw the cell at `p`.
+    pub(crate) fn shows(&self, p: Vec3) -> bool {
+        match self.mode {
+            ClipMode::Off => true,
+            ClipMode::Planes => {
+                p.cmpge(self.min).all()
+                    && p.cmple(self.max).all()
+                    && self.cut.is_none_or(|c| p.dot(c.normal()) <= c.offset)
+            }
+            // every layer is one cell thick, even when positions are
+            // halfway between whole numbers
+            ClipMode::Slice => {
+                let offset = p[self.slice_axis] - self.slice_at;
+                (-0.5..0.5).contains(&offset)
+            }
+        }
+    }
+}
+
+/// What to do when the main grid settles into a [`Cycle`].
+#[derive(Default, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+pub(crate) enum OnCycle {
+    #[default]
+    #[strum(serialize = "Keep running")]
+    Continue,
+    Pause,
+    /// Restart with the next noise seed, or the next seed of every layer.
+    /// Pauses instead if none of the layers are random.
+    Reseed,
+}
+
+/// Watches the main grid for still lifes and oscillators.
+#[derive(Resource)]
+pub(crate) struct Cycles {
+    detector: CycleDetector,
+    /// The last generation given to the detector.
+    last: Option<u64>,
+    pub(crate) on_cycle: OnCycle,
+}
+
+impl Default for Cycles {
+    fn default() -> Self {
+        Self {
+            detector: CycleDetector::new(Self::MAX_PERIOD),
+            last: None,
+            on_cycle: OnCycle::default(),
+        }
+    }
+}
+
+impl Cycles {
+    /// The longest period that's detected.
+    const MAX_PERIOD: usize = 1000;
+
+    pub(crate) fn cycle(&self) -> Option<Cycle> {
+        self.detector.cycle()
+    }
+}
+
+/// Saves the main grid to, or replaces it with, a [`Snapshot`] file.
+#[derive(Event)]
+pub(crate) enum SnapshotRequest {
+    Save(PathBuf),
+    Load(PathBuf),
+}
+
+/// The outcome of the last [`SnapshotRequest`], for the settings window.
+#[derive(Resource)]
+pub(crate) struct SnapshotStatus(pub(crate) Result<String, String>);
+
+impl Default for SnapshotStatus {
+    fn default() -> Self {
+        Self(Ok(String::new()))
+    }
+}
+
+/// Sent whenever the main grid is swapped for a different one, so work based
+/// on the old grid is dropped.
+#[derive(Event)]
+struct GridReplaced {
+    /// Whether the new grid is an earlier generation of the same run, from
+    /// the [`Timeline`], rather than the start of a new one.
+    rewound: bool,
+}
+
+/// Reseeds the main grid from the current [`NoiseSettings`] or [`Seed`]
+/// layers, as chosen in the [`GridSettings`].
+#[derive(Event)]
+pub struct GridReset;
+
+/// Marks the grid entity driven by the [`AutomataPlugin`].
+#[derive(Component)]
+pub struct MainGrid;
+
+/// The default key bindings: space plays or pauses, and the arrow keys step
+/// forwards and back.
+fn playback_keys(
+    keys: Res<Input<KeyCode>>,
+    mut contexts: EguiContexts,
+    mut controls: EventWriter<PlaybackControl>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Space) {
+        controls.send(PlaybackControl::TogglePause);
+    }
+    if keys.just_pressed(KeyCode::Right) {
+        controls.send(PlaybackControl::Step);
+    }
+    if keys.just_pressed(KeyCode::Left) {
+        controls.send(PlaybackControl::StepBack);
+    }
+}
+
+fn control_playback(
+    mut controls: EventReader<PlaybackControl>,
+    mut playback: ResMut<Playback>,
+    mut timer: ResMut<GridTimer>,
+) {
+    for control in controls.read() {
+        match *control {
+            PlaybackControl::Play => {
+                playback.paused = false;
+                playback.remaining = None;
+            }
+            PlaybackControl::Pause => playback.paused = true,
+            PlaybackControl::TogglePause => {
+                playback.paused = !playback.paused;
+                playback.remaining = None;
+            }
+            PlaybackControl::Step => {
+                playback.paused = true;
+                playback.remaining = None;
+                playback.steps += 1;
+            }
+            PlaybackControl::Seek(_) | PlaybackControl::StepBack => {
+                playback.paused = true;
+                playback.remaining = None;
+                playback.steps = 0;
+            }
+            PlaybackControl::Run(n) => {
+                playback.paused = n == 0;
+                playback.remaining = (n > 0).then_some(n);
+            }
+            PlaybackControl::SetInterval(interval) => {
+                playback.interval = interval;
+                timer.0.set_duration(interval);
+            }
+            PlaybackControl::SetMaxSpeed(on) => playback.max_speed = on,
+        }
+    }
+}
+
+/// Replaces the main grid with a past generation from the [`Timeline`].
+fn seek_history(
+    mut controls: EventReader<PlaybackControl>,
+    mut g: Query<&mut Grid, With<MainGrid>>,
+    mut generation: ResMut<Generation>,
+    mut timeline: ResMut<Timeline>,
+    mut replaced: EventWriter<GridReplaced>,
+) {
+    for control in controls.read() {
+        let target = match *control {
+            PlaybackControl::Seek(target) => target,
+            PlaybackControl::StepBack => generation.0.saturating_sub(1),
+            _ => continue,
+        };
+        let (Ok(mut g), Some(past)) = (
+            g.get_single_mut(),
+            timeline.history.as_ref().and_then(|h| h.get(target)),
+        ) else {
+            continue;
+        };
+        *g = past;
+        generation.0 = target;
+        timeline.shown = target;
+        replaced.send(GridReplaced { rewound: true });
+    }
+}
+
+/// Starts the [`Population`] over with the first generation of each new run.
+fn start_population(
+    g: Query<(Option<&Grid>, Option<&SparseGrid>), With<MainGrid>>,
+    rule: Res<Rule>,
+    generation: Res<Generation>,
+    mut population: ResMut<Population>,
+    mut replaced: EventReader<GridReplaced>,
+) {
+    if replaced.read().any(|r| !r.rewound) {
+        population.0.clear();
+    }
+    if !population.0.is_empty() {
+        return;
+    }
+    let stats = match g.get_single() {
+        Ok((Some(g), _)) => Stats::new(g, None, rule.states),
+        Ok((_, Some(g))) => Stats::new(g, None, rule.states),
+        _ => return,
+    };
+    population.record(generation.0, stats);
+}
+
+/// Feeds each new generation to the cycle detector, and acts on the cycles it
+/// finds.
+#[allow(clippy::too_many_arguments)]
+fn detect_cycles(
+    population: Res<Population>,
+    mut cycles: ResMut<Cycles>,
+    mut replaced: EventReader<GridReplaced>,
+    mut controls: EventWriter<PlaybackControl>,
+    mut n: ResMut<NoiseSettings>,
+    mut seed: ResMut<Seed>,
+    settings: Res<GridSettings>,
+    mut reset: EventWriter<GridReset>,
+) {
+    if replaced.read().last().is_some() {
+        cycles.last = None;
+    }
+    let Some(&(generation, ref stats)) = population.0.last() else {
+        return;
+    };
+    if cycles.last == Some(generation) {
+        return;
+    }
+    if cycles.last.map(|last| last + 1) != Some(generation) {
+        // a new run, or a rewound one: catch up on the generations so far
+        // without acting on cycles found in them
+        cycles.detector = CycleDetector::new(Cycles::MAX_PERIOD);
+        let start = population.0.len().saturating_sub(Cycles::MAX_PERIOD);
+        for (g, s) in &population.0[start..] {
+            cycles.detector.push(*g, s.fingerprint);
+        }
+        cycles.last = Some(generation);
+        return;
+    }
+    let was_cycling = cycles.cycle().is_some();
+    let cycle = cycles.detector.push(generation, stats.fingerprint);
+    cycles.last = Some(generation);
+    if was_cycling || cycle.is_none() {
+        return;
+    }
+    match cycles.on_cycle {
+        OnCycle::Continue => {}
+        OnCycle::Pause => controls.send(PlaybackControl::Pause),
+        OnCycle::Reseed if settings.layered => {
+            // shapes alone would restart the same way every time
+            if seed.reseed() {
+                reset.send(GridReset);
+            } else {
+                controls.send(PlaybackControl::Pause);
+            }
+        }
+        OnCycle::Reseed => {
+            n.seed = n.seed.wrapping_add(1);
+            reset.send(GridReset);
+        }
+    }
+}
+
+/// Adds each new generation of the main grid to the [`Timeline`], starting
+/// over whenever a new run begins.
+fn record_history(
+    g: Query<Ref<Grid>, With<MainGrid>>,
+    generation: Res<Generation>,
+    mut timeline: ResMut<Timeline>,
+    mut replaced: EventReader<GridReplaced>,
+) {
+    if replaced.read().any(|r| !r.rewound) {
+        timeline.history = None;
+    }
+    let Ok(g) = g.get_single() else {
+        timeline.history = None;
+        return;
+    };
+    if !g.is_changed() && timeline.history.is_some() {
+        return;
+    }
+    let (shown, capacity) = (timeline.shown, timeline.capacity);
+    match &mut timeline.history {
+        // rewound to a generation that's already recorded
+        Some(_) if generation.0 == shown => {}
+        // stepped on, possibly from a rewound generation
+        Some(h) if generation.0 == shown + 1 && (h.first()..=h.latest()).contains(&shown) => {
+            h.truncate(shown);
+            h.push(&g);
+        }
+        _ => timeline.history = Some(History::new(&g, generation.0, capacity)),
+    }
+    timeline.shown = generation.0;
+}
+
+fn create_grid(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    noise: Res<NoiseSettings>,
+    seed: Res<Seed>,
+    settings: Res<GridSettings>,
+) {
+    commands.spawn((
+        settings.seed(&noise, &seed),
+        MainGrid,
+        SpatialBundle::INHERITED_IDENTITY,
+        meshes.add(Mesh::from(shape::Cube { size: 0.8 })),
+        NoFrustumCulling,
+        InstanceMaterialData(vec![]),
+    ));
+}
+
+/// Replaces the main grid with a freshly seeded one of the configured kind.
+#[allow(clippy::too_many_arguments)]
+fn reset_grid(
+    mut commands: Commands,
+    g: Query<Entity, With<MainGrid>>,
+    mut ev: EventReader<GridReset>,
+    n: Res<NoiseSettings>,
+    seed: Res<Seed>,
+    settings: Res<GridSettings>,
+    mut generation: ResMut<Generation>,
+    mut replaced: EventWriter<GridReplaced>,
+) {
+    let Ok(g) = g.get_single() else {
+        return;
+    };
+    if ev.read().last().is_none() {
+        return;
+    }
+    generation.0 = 0;
+    replaced.send(GridReplaced { rewound: false });
+    let grid = settings.seed(&n, &seed);
+    let mut g = commands.entity(g);
+    g.remove::<(Grid, SparseGrid)>();
+    if settings.unbounded {
+        g.insert(SparseGrid::from(&grid));
+    } else {
+        g.insert(grid);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn snapshot_io(
+    mut commands: Commands,
+    g: Query<(Entity, Option<&Grid>), With<MainGrid>>,
+    mut requests: EventReader<SnapshotRequest>,
+    mut rule: ResMut<Rule>,
+    mut generation: ResMut<Generation>,
+    mut settings: ResMut<GridSettings>,
+    mut status: ResMut<SnapshotStatus>,
+    mut replaced: EventWriter<GridReplaced>,
+) {
+    let Ok((entity, grid)) = g.get_single() else {
+        return;
+    };
+    for request in requests.read() {
+        status.0 = match (request, grid) {
+            (SnapshotRequest::Save(_), None) => Err("unbounded grids can't be saved".into()),
+            (SnapshotRequest::Save(path), Some(grid)) => {
+                let snapshot = Snapshot {
+                    grid: grid.clone(),
+                    rule: rule.clone(),
+                    generation: generation.0,
+                };
+                File::create(path)
+                    .and_then(|f| {
+                        let mut f = BufWriter::new(f);
+                        snapshot.write(&mut f)?;
+                        f.flush()
+                    })
+                    .map(|_| format!("saved generation {}", generation.0))
+                    .map_err(|e| e.to_string())
+            }
+            (SnapshotRequest::Load(path), _) => File::open(path)
+                .map_err(SnapshotError::Io)
+                .and_then(|f| Snapshot::read(&mut BufReader::new(f)))
+                .map(|snapshot| {
+                    *rule = snapshot.rule;
+                    generation.0 = snapshot.generation;
+                    settings.dims = snapshot.grid.dims();
+                    settings.unbounded = false;
+                    replaced.send(GridReplaced { rewound: false });
+                    commands
+                        .entity(entity)
+                        .remove::<SparseGrid>()
+                        .insert(snapshot.grid);
+                    format!("loaded generation {}", generation.0)
+                })
+                .map_err(|e| e.to_string()),
+        };
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_grid<G: Automaton + Component>(
+    mut g: Query<&mut G, With<MainGrid>>,
+    rule: Res<Rule>,
+    time: Res<Time>,
+    mut timer: ResMut<GridTimer>,
+    mut task: Local<Option<Task<(G, Stats)>>>,
+    mut ev: EventReader<GridReplaced>,
+    boundary: Res<Boundary>,
+    mut generation: ResMut<Generation>,
+    mut playback: ResMut<Playback>,
+    mut population: ResMut<Population>,
+) {
+    // the old generation in flight is stale once the grid is replaced
+    if ev.read().last().is_some() {
+        task.take().map(|t| block_on(t.cancel()));
+        return;
+    }
+    let Ok(mut g) = g.get_single_mut() else {
+        return;
+    };
+    // and once the settings it was computed with change
+    if rule.is_changed() || boundary.is_changed() {
+        task.take().map(|t| block_on(t.cancel()));
+    }
+    let spawn = |g: &G| {
+        let g = g.clone();
+        let rule = rule.clone();
+        let boundary = *boundary;
+        AsyncComputeTaskPool::get().spawn(async move {
+            let next = g.next(&rule, boundary);
+            let stats = Stats::new(&next, Some(&g), rule.states);
+            (next, stats)
+        })
+    };
+    // the next generation is always computed ahead of time, so steps and
+    // timer ticks only have to swap it in
+    let Some(next) = task.as_ref() else {
+        *task = Some(spawn(&g));
+        return;
+    };
+    let due = if playback.steps > 0 {
+        playback.steps -= 1;
+        true
+    } else if playback.paused {
+        false
+    } else if playback.max_speed {
+        next.is_finished()
+    } else {
+        timer.0.tick(time.delta()).finished()
+    };
+    if !due {
+        return;
+    }
+    if let Some((next, stats)) = task.take().map(block_on) {
+        *g = next;
+        generation.0 += 1;
+        population.record(generation.0, stats);
+    }
+    if let Some(remaining) = &mut playback.remaining {
+        *remaining -= 1;
+        if *remaining == 0 {
+            playback.remaining = None;
+            playback.paused = true;
+        }
+    }
+    *task = Some(spawn(&g));
+}
+
+fn render_grid_data<G: Automaton + Component>(
+    mut g: Query<(&mut InstanceMaterialData, &G)>,
+    rule: Res<Rule>,
+    clipping: Res<Clipping>,
+    cull: Res<CullHidden>,
+) {
+    for (mut dat, g) in g.iter_mut() {
+        let center = Vec3::from(g.center());
+        let position = |p: [i64; 3]| Vec3::from(p.map(|c| c as f32)) - center;
+        let drawn = |p| clipping.shows(position(p));
+        let cells = if cull.0 {
+            visible_cells(g, drawn)
+        } else {
+            g.live_cells().filter(|(p, _)| drawn(*p)).collect()
+        };
+        *dat = InstanceMaterialData(
+            cells
+                .into_iter()
+                .map(|(p, c)| InstanceData {
+                    position: position(p),
+                    scale: 1.,
+                    color: c.color_grad(&rule.states).into(),
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_with_a_boundary_changed_while_paused() {
+        let rule: Rule = "4/4/5/M".parse().unwrap();
+        let grid = Grid::new_noise([12; 3], &NoiseSettings::default());
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(rule.clone())
+            .insert_resource(Boundary::DeadWall)
+            .insert_resource(GridTimer(Timer::new(
+                Playback::default().interval,
+                TimerMode::Repeating,
+            )))
+            .insert_resource(Playback {
+                paused: true,
+                ..default()
+            })
+            .init_resource::<Generation>()
+            .init_resource::<Population>()
+            .add_event::<GridReplaced>()
+            .add_systems(Update, update_grid::<Grid>);
+        app.world.spawn((grid.clone(), MainGrid));
+        // starts computing the next generation on the old boundary
+        app.update();
+
+        *app.world.resource_mut::<Boundary>() = Boundary::AliveWall;
+        app.world.resource_mut::<Playback>().steps = 1;
+        while app.world.resource::<Generation>().0 == 0 {
+            app.update();
+        }
+        let stepped = app
+            .world
+            .query_filtered::<&Grid, With<MainGrid>>()
+            .single(&app.world);
+        let expected = grid.next(&rule, Boundary::AliveWall);
+        assert!(stepped.iter().eq(expected.iter()));
+        assert!(!stepped
+            .iter()
+            .eq(grid.next(&rule, Boundary::DeadWall).iter()));
+    }
+}
@@ -0,0 +1,245 @@
+//! Named combinations of a rule and the seed that shows it off, and a plain
+//! text format for sharing them.
+//!
+//! A preset file is a list of `[Name]` sections, each followed by
+//! `key: value` lines:
+//!
+//! ```text
+//! # comments and blank lines are ignored
+//! [445]
+//! description: Grows into a jagged, decaying shell that eventually burns itself out.
+//! rule: 4/4/5/M
+//! seed: 1
+//! threshold: 0.1
+//! core size: 10
+//! size: 50
+//! ```
+//!
+//! Only the rule is required. The rule may be in any [`Notation`](crate::Notation),
+//! and the size is either a single side length or `WxHxD`. The noise can also
+//! be shaped with `frequency`, `offset: x, y, z`, `scale: x, y, z` and
+//! `core shape`, which are only written when they differ from the defaults.
+
+use crate::{
+    grid::{parse_dims, parse_triple, NoiseSettings},
+    rule::Rule,
+};
+use std::fmt;
+
+/// The presets shipped with the crate.
+const BUILTIN: &str = include_str!("../presets.txt");
+
+/// A named rule with the noise settings and grid size to seed it with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub description: String,
+    pub rule: Rule,
+    pub noise: NoiseSettings,
+    pub dims: [usize; 3],
+}
+
+impl Preset {
+    /// The built-in catalog of well-known rules.
+    pub fn builtin() -> Vec<Preset> {
+        Self::parse_list(BUILTIN).expect("built-in presets are valid")
+    }
+
+    /// Parses every preset in a preset file. Errors name the offending line.
+    pub fn parse_list(s: &str) -> Result<Vec<Preset>, String> {
+        let mut presets = Vec::new();
+        // the preset being read, and whether its rule has been seen yet
+        let mut current: Option<(Preset, usize)> = None;
+        let mut finish = |current: Option<(Preset, usize)>, rule_seen: bool| match current {
+            Some((p, line)) if !rule_seen => Err(format!("line {line}: {} has no rule", p.name)),
+            Some((p, _)) => {
+                presets.push(p);
+                Ok(())
+            }
+            None => Ok(()),
+        };
+        let mut rule_seen = false;
+        for (i, line) in s.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |msg: String| format!("line {line_no}: {msg}");
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                finish(current.take(), rule_seen)?;
+                rule_seen = false;
+                current = Some((
+                    Preset {
+                        name: name.trim().into(),
+                        description: String::new(),
+                        rule: "4/4/5/M".parse().unwrap(),
+                        noise: NoiseSettings::default(),
+                        dims: [50; 3],
+                    },
+                    line_no,
+                ));
+                continue;
+            }
+            let Some((p, _)) = &mut current else {
+                return Err(err("expected a [name] before any settings".into()));
+            };
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| err(format!("expected `key: value`, found `{line}`")))?;
+            let (key, value) = (key.trim(), value.trim());
+            let invalid = || err(format!("invalid {key}: {value}"));
+            match key {
+                "description" => p.description = value.into(),
+                "rule" => {
+                    p.rule = value
+                        .parse()
+                        .map_err(|e| err(format!("invalid rule:\n{e}")))?;
+                    rule_seen = true;
+                }
+                "seed" => p.noise.seed = value.parse().map_err(|_| invalid())?,
+                "threshold" => p.noise.threshold = value.parse().map_err(|_| invalid())?,
+                "core size" => p.noise.size = value.parse().map_err(|_| invalid())?,
+                "core shape" => p.noise.shape = value.parse().map_err(|_| invalid())?,
+                "frequency" => p.noise.frequency = value.parse().map_err(|_| invalid())?,
+                "offset" => p.noise.offset = parse_triple(value).ok_or_else(invalid)?,
+                "scale" => p.noise.scale = parse_triple(value).ok_or_else(invalid)?,
+                "size" => p.dims = parse_dims(value).ok_or_else(invalid)?,
+                key => return Err(err(format!("unknown setting `{key}`"))),
+            }
+        }
+        finish(current, rule_seen)?;
+        Ok(presets)
+    }
+
+    /// Writes `presets` in the format read by [`Preset::parse_list`].
+    pub fn write_list(presets: &[Preset]) -> String {
+        presets
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl fmt::Display for Preset {
+    /// Writes the preset as a section of a preset file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[{}]", self.name)?;
+        if !self.description.is_empty() {
+            writeln!(f, "description: {}", self.description)?;
+        }
+        writeln!(f, "rule: {}", self.rule)?;
+        writeln!(f, "seed: {}", self.noise.seed)?;
+        writeln!(f, "threshold: {}", self.noise.threshold)?;
+        writeln!(f, "core size: {}", self.noise.size)?;
+        let default = NoiseSettings::default();
+        if self.noise.shape != default.shape {
+            writeln!(f, "core shape: {}", self.noise.shape)?;
+        }
+        if self.noise.frequency != default.frequency {
+            writeln!(f, "frequency: {}", self.noise.frequency)?;
+        }
+        for (key, value, default) in [
+            ("offset", self.noise.offset, default.offset),
+            ("scale", self.noise.scale, default.scale),
+        ] {
+            if value != default {
+                writeln!(f, "{key}: {}, {}, {}", value[0], value[1], value[2])?;
+            }
+        }
+        match self.dims {
+            [w, h, d] if w == h && h == d => writeln!(f, "size: {w}"),
+            [w, h, d] => writeln!(f, "size: {w}x{h}x{d}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::CoreShape;
+
+    #[test]
+    fn builtin_presets() {
+        let presets = Preset::builtin();
+        for name in [
+            "445",
+            "Amoeba",
+            "Builder",
+            "Clouds",
+            "Crystal Growth",
+            "Pyroclastic",
+        ] {
+            assert!(presets.iter().any(|p| p.name == name), "{name} missing");
+        }
+        for p in &presets {
+            assert!(!p.description.is_empty(), "{} has no description", p.name);
+        }
+        assert_eq!(
+            Preset::parse_list(&Preset::write_list(&presets)),
+            Ok(presets)
+        );
+    }
+
+    #[test]
+    fn parse_preset_file() {
+        let presets = Preset::parse_list(
+            "# shared presets\n\
+             [Flat]\n\
+             rule: B3/S2-3\n\
+             size: 80x80x3\n\
+             \n\
+             [ Noisy ]\n\
+             threshold: -0.5\n\
+             rule: 4/4/5/N2\n",
+        )
+        .unwrap();
+        assert_eq!(presets.len(), 2);
+        assert_eq!(presets[0].name, "Flat");
+        assert_eq!(presets[0].rule, "2-3/3/2/M".parse().unwrap());
+        assert_eq!(presets[0].dims, [80, 80, 3]);
+        assert_eq!(presets[0].noise, NoiseSettings::default());
+        assert_eq!(presets[1].name, "Noisy");
+        assert_eq!(presets[1].noise.threshold, -0.5);
+        assert_eq!(presets[1].dims, [50; 3]);
+        assert_eq!(Preset::parse_list(""), Ok(vec![]));
+
+        let shaped = Preset::parse_list(
+            "[Slab]\n\
+             rule: 4/4/5/M\n\
+             core shape: ellipsoid:12x12x3\n\
+             frequency: 0.25\n\
+             offset: 0, 0.5, -2\n\
+             scale: 1,1,4\n",
+        )
+        .unwrap();
+        let noise = &shaped[0].noise;
+        assert_eq!(noise.shape, CoreShape::Ellipsoid([12, 12, 3]));
+        assert_eq!(noise.frequency, 0.25);
+        assert_eq!((noise.offset, noise.scale), ([0., 0.5, -2.], [1., 1., 4.]));
+        assert_eq!(Preset::parse_list(&Preset::write_list(&shaped)), Ok(shaped));
+    }
+
+    #[test]
+    fn preset_file_errors() {
+        let err = |s: &str| Preset::parse_list(s).unwrap_err();
+        assert_eq!(
+            err("rule: 4/4/5/M"),
+            "line 1: expected a [name] before any settings"
+        );
+        assert_eq!(
+            err("[A]\nrule: 4/4/5/M\n[B]\nseed: 2"),
+            "line 3: B has no rule"
+        );
+        assert_eq!(err("[A]\n\nseed: x"), "line 3: invalid seed: x");
+        assert_eq!(err("[A]\nsize: 2x3"), "line 2: invalid size: 2x3");
+        assert_eq!(err("[A]\noffset: 1,2"), "line 2: invalid offset: 1,2");
+        assert_eq!(err("[A]\ncolor: red"), "line 2: unknown setting `color`");
+        assert_eq!(
+            err("[A]\noops"),
+            "line 2: expected `key: value`, found `oops`"
+        );
+        assert!(err("[A]\nrule: 4/4/1/M").starts_with("line 2: invalid rule:\n"));
+    }
+}
@@ -28,6 +28,8 @@ use bevy::{
 };
 use bytemuck::{Pod, Zeroable};
 
+use crate::cell::CellStatus;
+
 #[derive(Component, Deref)]
 pub struct InstanceMaterialData(pub Vec<InstanceData>);
 
@@ -247,3 +249,31 @@ impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
         RenderCommandResult::Success
     }
 }
+
+impl CellStatus {
+    #[allow(dead_code)]
+    pub fn color(&self) -> Color {
+        match self {
+            Self::Dead => Color::rgba(0., 0., 0., 0.),
+            Self::Alive => Color::WHITE,
+            _ => Color::GRAY,
+        }
+    }
+
+    pub fn color_grad(&self, states: &u8) -> Color {
+        const C1: Color = Color::hsl(359.9, 1., 0.5);
+        const C2: Color = Color::hsl(300., 1., 0.);
+        match self {
+            Self::Dead => C2,
+            Self::Alive => C1,
+            Self::Dying { health } => {
+                let h1 = C1.h();
+                let h2 = C2.h();
+                let weight = *health as f32 / *states as f32;
+                let l = weight / 2.;
+                let h = (1. - weight) * h1 + weight * h2;
+                Color::hsl(h, 1., l)
+            }
+        }
+    }
+}
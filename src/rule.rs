@@ -1,23 +1,25 @@
 use crate::cell::CellStatus;
+#[cfg(feature = "viewer")]
 use bevy::{prelude::Resource, reflect::Reflect};
-use std::ops::Range;
+use itertools::iproduct;
+use std::{fmt, ops::Range};
+use strum::{Display, EnumIter};
 
-#[derive(Resource, Clone, Reflect, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "viewer", derive(Resource, Reflect))]
 pub struct Rule {
-    pub survival: Vec<Range<u8>>,
-    pub birth: Vec<Range<u8>>,
+    pub survival: Vec<Range<u16>>,
+    pub birth: Vec<Range<u16>>,
     pub states: u8,
     pub neighbors: Neighbors,
 }
 
 impl Rule {
     pub fn passes_survive(&self, count: usize) -> bool {
-        let count = count as u8;
         rule_contains(count, &self.survival)
     }
 
     pub fn passes_birth(&self, count: usize) -> bool {
-        let count = count as u8;
         rule_contains(count, &self.birth)
     }
 
@@ -30,95 +32,890 @@ impl Rule {
     }
 }
 
-fn rule_contains(n: u8, range: &[Range<u8>]) -> bool {
-    range.iter().any(|r| r.contains(&n))
+fn rule_contains(n: usize, range: &[Range<u16>]) -> bool {
+    u16::try_from(n).is_ok_and(|n| range.iter().any(|r| r.contains(&n)))
 }
 
-#[derive(Debug, Clone, Copy, Reflect, PartialEq, Eq)]
+/// Sorts `ranges`, drops empty ones and merges those that overlap or touch,
+/// leaving the shortest list that contains the same counts.
+pub(crate) fn merge_ranges(ranges: &[Range<u16>]) -> Vec<Range<u16>> {
+    let mut ranges = ranges
+        .iter()
+        .filter(|r| !r.is_empty())
+        .cloned()
+        .collect::<Vec<_>>();
+    ranges.sort_by_key(|r| r.start);
+    let mut merged = Vec::<Range<u16>>::with_capacity(ranges.len());
+    for r in ranges {
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+/// Writes ranges as comma-separated counts, with `a{to}b` for runs of
+/// several.
+fn fmt_ranges(f: &mut fmt::Formatter<'_>, ranges: &[Range<u16>], to: &str) -> fmt::Result {
+    for (i, r) in merge_ranges(ranges).into_iter().enumerate() {
+        if i > 0 {
+            write!(f, ",")?;
+        }
+        match r.end - r.start {
+            1 => write!(f, "{}", r.start)?,
+            _ => write!(f, "{}{to}{}", r.start, r.end - 1)?,
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Rule {
+    /// Formats the rule in [`Notation::Standard`] with ranges merged, e.g.
+    /// `4-5/4,6/5/M`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.notated(Notation::Standard).unwrap().fmt(f)
+    }
+}
+
+/// The ways of writing a rule that [`Rule`] parses, told apart by how they
+/// start.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+pub enum Notation {
+    /// `survival/birth/states/neighbors`, e.g. `4/4/5/M`.
+    #[default]
+    Standard,
+    /// Birth first with letters, as in many 3D automata tools, e.g.
+    /// `B4/S4/C5/M`.
+    #[strum(serialize = "Birth/Survival")]
+    BirthSurvival,
+    /// Golly's `3D` rules, which only have two states and a few
+    /// neighborhoods, e.g. `3D4/4`.
+    #[strum(serialize = "Golly 3D")]
+    Golly,
+}
+
+impl Notation {
+    /// Which notation `s` is written in, going by how it starts.
+    pub fn detect(s: &str) -> Self {
+        if s.starts_with("3D") {
+            Self::Golly
+        } else if s.starts_with(['B', 'S']) {
+            Self::BirthSurvival
+        } else {
+            Self::Standard
+        }
+    }
+}
+
+/// A [`Rule`] written in a particular [`Notation`].
+pub struct Notated<'a>(&'a Rule, Notation);
+
+impl fmt::Display for Notated<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(rule, notation) = self;
+        match notation {
+            Notation::Standard => {
+                fmt_ranges(f, &rule.survival, "-")?;
+                write!(f, "/")?;
+                fmt_ranges(f, &rule.birth, "-")?;
+                write!(f, "/{}/{}", rule.states, rule.neighbors)
+            }
+            Notation::BirthSurvival => {
+                write!(f, "B")?;
+                fmt_ranges(f, &rule.birth, "-")?;
+                write!(f, "/S")?;
+                fmt_ranges(f, &rule.survival, "-")?;
+                write!(f, "/C{}/{}", rule.states, rule.neighbors)
+            }
+            Notation::Golly => {
+                write!(f, "3D")?;
+                fmt_ranges(f, &rule.survival, "..")?;
+                write!(f, "/")?;
+                fmt_ranges(f, &rule.birth, "..")?;
+                match &rule.neighbors {
+                    Neighbors::Neumann(1) => write!(f, "F"),
+                    n if *n == Neighbors::edges() => write!(f, "E"),
+                    n if *n == Neighbors::corners() => write!(f, "C"),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Rule {
+    /// The rule written in `notation`, or `None` if the notation can't
+    /// express it.
+    pub fn notated(&self, notation: Notation) -> Option<Notated<'_>> {
+        let expressible = match notation {
+            Notation::Standard | Notation::BirthSurvival => true,
+            Notation::Golly => {
+                self.states == 2
+                    && (self.neighbors == Neighbors::Moore(1)
+                        || self.neighbors == Neighbors::Neumann(1)
+                        || self.neighbors == Neighbors::edges()
+                        || self.neighbors == Neighbors::corners())
+            }
+        };
+        expressible.then_some(Notated(self, notation))
+    }
+}
+
+/// The largest radius of a Moore or von Neumann neighborhood, as the offsets
+/// of its cells are stored as `i8`.
+pub const MAX_RADIUS: u8 = i8::MAX as u8;
+
+/// The shape of a cell's neighborhood, with its radius.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "viewer", derive(Reflect))]
 pub enum Neighbors {
-    Moore,
-    Neumann,
+    /// Every cell within the surrounding cube.
+    Moore(u8),
+    /// Every cell within the given Manhattan distance.
+    Neumann(u8),
+    /// An arbitrary set of offsets, sorted and without the origin. Build with
+    /// [`Neighbors::custom`].
+    Custom(Vec<[i8; 3]>),
+}
+
+impl Neighbors {
+    pub fn custom(offsets: impl IntoIterator<Item = [i8; 3]>) -> Self {
+        let mut offsets = offsets
+            .into_iter()
+            .filter(|o| *o != [0, 0, 0])
+            .collect::<Vec<_>>();
+        offsets.sort();
+        offsets.dedup();
+        Self::Custom(offsets)
+    }
+
+    /// The 6 face and 12 edge neighbors.
+    pub fn face_edge() -> Self {
+        Self::custom(
+            Self::Moore(1)
+                .offsets()
+                .into_iter()
+                .filter(|o| o.contains(&0)),
+        )
+    }
+
+    /// The 12 edge neighbors.
+    pub fn edges() -> Self {
+        Self::custom(
+            Self::Moore(1)
+                .offsets()
+                .into_iter()
+                .filter(|o| o.iter().filter(|c| **c == 0).count() == 1),
+        )
+    }
+
+    /// The 8 corner neighbors.
+    pub fn corners() -> Self {
+        Self::custom(
+            Self::Moore(1)
+                .offsets()
+                .into_iter()
+                .filter(|o| o.iter().all(|c| *c != 0)),
+        )
+    }
+
+    /// Distance of the furthest neighbor along any single axis.
+    pub fn radius(&self) -> u8 {
+        match self {
+            Self::Moore(r) | Self::Neumann(r) => *r,
+            Self::Custom(offsets) => offsets
+                .iter()
+                .flatten()
+                .map(|c| c.unsigned_abs())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Number of cells in this neighborhood, the length of
+    /// [`offsets`](Self::offsets) without building them.
+    pub fn count(&self) -> usize {
+        match self {
+            Self::Moore(r) => (2 * *r as usize + 1).pow(3) - 1,
+            // the octahedral number of the radius, less the origin
+            Self::Neumann(r) => {
+                let r = *r as usize;
+                (2 * r + 1) * (2 * r * r + 2 * r + 3) / 3 - 1
+            }
+            Self::Custom(offsets) => offsets.len(),
+        }
+    }
+
+    /// Relative positions of every cell in this neighborhood. Moore and
+    /// custom neighborhoods are in ascending order. Von Neumann neighborhoods
+    /// are grouped by the last axis they're off the origin along, then
+    /// ordered by z, y and x, so the six face neighbors come as -x, +x, -y,
+    /// +y, -z, +z.
+    ///
+    /// # Panics
+    ///
+    /// If the radius is over [`MAX_RADIUS`], as the offsets wouldn't fit.
+    pub fn offsets(&self) -> Vec<[i8; 3]> {
+        assert!(
+            self.radius() <= MAX_RADIUS,
+            "radius {} is over {MAX_RADIUS}",
+            self.radius()
+        );
+        // summed in i16, as the distance of a corner can be three radii
+        let r = i16::from(self.radius());
+        let cube = || {
+            iproduct!(-r..=r, -r..=r, -r..=r)
+                .filter(|o| *o != (0, 0, 0))
+                .map(|(x, y, z)| [x, y, z])
+        };
+        let narrow = |o: [i16; 3]| o.map(|c| c as i8);
+        match self {
+            Self::Custom(offsets) => offsets.clone(),
+            Self::Moore(_) => cube().map(narrow).collect(),
+            Self::Neumann(_) => {
+                let mut offsets = cube()
+                    .filter(|o| o.iter().map(|c| c.abs()).sum::<i16>() <= r)
+                    .map(narrow)
+                    .collect::<Vec<_>>();
+                offsets.sort_by_key(|&[x, y, z]| {
+                    let axis = [x, y, z].iter().rposition(|c| *c != 0);
+                    (axis, z, y, x)
+                });
+                offsets
+            }
+        }
+    }
 }
 
+impl fmt::Display for Neighbors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Moore(1) => write!(f, "M"),
+            Self::Moore(r) => write!(f, "M{r}"),
+            Self::Neumann(1) => write!(f, "N"),
+            Self::Neumann(r) => write!(f, "N{r}"),
+            Self::Custom(offsets) => {
+                write!(f, "C")?;
+                offsets
+                    .iter()
+                    .try_for_each(|[x, y, z]| write!(f, "({x},{y},{z})"))
+            }
+        }
+    }
+}
+
+pub use parser::{Diagnostic, ParseRuleError};
+
 mod parser {
-    use std::str::FromStr;
+    use std::{fmt, ops::Range, str::FromStr};
 
-    use super::{Neighbors, Rule};
+    use super::{merge_ranges, Neighbors, Notation, Rule, MAX_RADIUS};
     use chumsky::{
-        prelude::Simple,
+        error::SimpleReason,
+        prelude::{end, Simple},
         primitive::{choice, just},
         text, Parser,
     };
 
+    /// A problem with a rule string, and the bytes of the string it concerns.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Diagnostic {
+        pub span: Range<usize>,
+        pub message: String,
+    }
+
+    /// Everything wrong with a rule string.
+    ///
+    /// Displays as the rule string followed by each problem, with carets
+    /// under the part of the string it concerns.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ParseRuleError {
+        source: String,
+        pub diagnostics: Vec<Diagnostic>,
+    }
+
+    impl fmt::Display for ParseRuleError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.source)?;
+            for d in &self.diagnostics {
+                let pad = self.source[..d.span.start].chars().count();
+                let width = self.source[d.span.clone()].chars().count().max(1);
+                write!(
+                    f,
+                    "\n{:pad$}{} {}",
+                    "",
+                    "^".repeat(width),
+                    d.message,
+                    pad = pad
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    impl std::error::Error for ParseRuleError {}
+
+    impl ParseRuleError {
+        fn new(source: &str, errors: Vec<Simple<char>>, mut diagnostics: Vec<Diagnostic>) -> Self {
+            // chumsky counts chars, but the spans are used to slice the string
+            let byte = |c: usize| {
+                source
+                    .char_indices()
+                    .nth(c)
+                    .map_or(source.len(), |(i, _)| i)
+            };
+            diagnostics.extend(errors.into_iter().map(|e| Diagnostic {
+                span: byte(e.span().start)..byte(e.span().end),
+                message: describe(&e),
+            }));
+            diagnostics.sort_by_key(|d| (d.span.start, d.span.end));
+            diagnostics.dedup();
+            Self {
+                source: source.into(),
+                diagnostics,
+            }
+        }
+    }
+
+    fn describe(e: &Simple<char>) -> String {
+        if let SimpleReason::Custom(message) = e.reason() {
+            return message.clone();
+        }
+        let name = |c: Option<&char>| c.map_or("end of input".into(), |c| format!("'{c}'"));
+        let mut expected = e
+            .expected()
+            .filter(|c| !c.is_some_and(|c| c.is_ascii_digit()))
+            .map(|c| name(c.as_ref()))
+            .collect::<Vec<_>>();
+        if e.expected().any(|c| c.is_some_and(|c| c.is_ascii_digit())) {
+            expected.push("a number".into());
+        }
+        expected.sort();
+        match &expected[..] {
+            [] => format!("unexpected {}", name(e.found())),
+            [one] => format!("unexpected {}, expected {one}", name(e.found())),
+            [init @ .., last] => format!(
+                "unexpected {}, expected {} or {last}",
+                name(e.found()),
+                init.join(", ")
+            ),
+        }
+    }
+
+    /// A parsed rule, with the positions of the parts that are checked once
+    /// the whole rule is known.
+    struct Spanned {
+        survival: Vec<(Range<u16>, Range<usize>)>,
+        birth: Vec<(Range<u16>, Range<usize>)>,
+        /// `None` if the number was too large, which is already reported.
+        states: (Option<u8>, Range<usize>),
+        neighbors: Neighbors,
+    }
+
+    impl Spanned {
+        fn check(&self) -> Vec<Diagnostic> {
+            let mut diagnostics = Vec::new();
+            let (states, span) = &self.states;
+            if states.is_some_and(|s| s < 2) {
+                diagnostics.push(Diagnostic {
+                    span: span.clone(),
+                    message: "a rule needs at least 2 states".into(),
+                });
+            }
+            let max = self.neighbors.count();
+            for (range, span) in self.survival.iter().chain(&self.birth) {
+                if !range.is_empty() && range.start as usize > max {
+                    diagnostics.push(Diagnostic {
+                        span: span.clone(),
+                        message: format!(
+                            "{} has only {max} neighbors, so this can never match",
+                            self.neighbors
+                        ),
+                    });
+                }
+            }
+            diagnostics
+        }
+
+        fn into_rule(self) -> Rule {
+            let ranges = |r: Vec<(Range<u16>, _)>| {
+                merge_ranges(&r.into_iter().map(|(r, _)| r).collect::<Vec<_>>())
+            };
+            Rule {
+                survival: ranges(self.survival),
+                birth: ranges(self.birth),
+                states: self.states.0.unwrap_or(u8::MAX),
+                neighbors: self.neighbors,
+            }
+        }
+    }
+
     impl FromStr for Rule {
-        type Err = Simple<char>;
+        type Err = ParseRuleError;
 
+        /// Parses a rule in any [`Notation`], telling them apart by how
+        /// they start.
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            Self::parser()
-                .parse(s)
-                .map_err(|v| v.into_iter().next().unwrap())
+            let (rule, errors) = Self::parser(Notation::detect(s)).parse_recovery(s);
+            let diagnostics = rule.as_ref().map(Spanned::check).unwrap_or_default();
+            match rule {
+                Some(rule) if errors.is_empty() && diagnostics.is_empty() => Ok(rule.into_rule()),
+                _ => Err(ParseRuleError::new(s, errors, diagnostics)),
+            }
         }
     }
 
+    /// An unsigned number no larger than `max`. Anything larger is reported
+    /// and parsed as `None`, rather than failing the whole parse.
+    fn number<T: TryFrom<u64>>(
+        what: &'static str,
+        max: u64,
+    ) -> impl Parser<char, Option<T>, Error = Simple<char>> + Clone {
+        text::int(10).validate(move |s: String, span, emit| {
+            let n = s.parse::<u64>().ok().filter(|n| *n <= max);
+            let n = n.and_then(|n| T::try_from(n).ok());
+            if n.is_none() {
+                emit(Simple::custom(span, format!("{what} can be at most {max}")));
+            }
+            n
+        })
+    }
+
+    type Ranges = Vec<(Range<u16>, Range<usize>)>;
+
+    /// Comma-separated counts and ranges of counts, written `a-b` or `a..b`.
+    fn ranges() -> impl Parser<char, Ranges, Error = Simple<char>> + Clone {
+        let count = number::<u16>("a neighbor count", u16::MAX as u64 - 1);
+        count
+            .clone()
+            .then(just("-").or(just("..")).ignore_then(count).or_not())
+            .validate(|(low, high), span, emit| match (low, high) {
+                (Some(low), Some(Some(high))) if high < low => {
+                    emit(Simple::custom(
+                        span,
+                        format!("range is backwards, did you mean {high}-{low}?"),
+                    ));
+                    low..low
+                }
+                // counts are below u16::MAX, so this can't overflow
+                (Some(low), Some(Some(high))) => low..high + 1,
+                (Some(low), None) => low..low + 1,
+                // too large, which is already reported
+                _ => 0..0,
+            })
+            .map_with_span(|r, span| (r, span))
+            .separated_by(just(','))
+    }
+
+    fn states() -> impl Parser<char, (Option<u8>, Range<usize>), Error = Simple<char>> + Clone {
+        number::<u8>("the number of states", u8::MAX as u64).map_with_span(|n, span| (n, span))
+    }
+
+    /// `M`, `N` or their longer names `NN` and `VN` with an optional radius,
+    /// or `C` followed by offsets.
+    fn neighbors() -> impl Parser<char, Neighbors, Error = Simple<char>> + Clone {
+        let radius = number::<u8>("a radius", MAX_RADIUS.into())
+            .or_not()
+            .map(|r| r.flatten().unwrap_or(1));
+        let offset =
+            just('-')
+                .or_not()
+                .then(text::int(10))
+                .validate(|(neg, s): (_, String), span, emit| {
+                    let c = s
+                        .parse::<i16>()
+                        .ok()
+                        .map(|c| if neg.is_some() { -c } else { c });
+                    let max = i16::from(MAX_RADIUS);
+                    match c.filter(|c| (-max..=max).contains(c)) {
+                        Some(c) => c as i8,
+                        None => {
+                            emit(Simple::custom(span, "offsets must be between -127 and 127"));
+                            0
+                        }
+                    }
+                });
+        let offsets = offset
+            .separated_by(just(','))
+            .exactly(3)
+            .delimited_by(just('('), just(')'))
+            .map(|o| [o[0], o[1], o[2]])
+            .repeated();
+        choice((
+            just("NN")
+                .or(just("M"))
+                .ignore_then(radius.clone())
+                .map(Neighbors::Moore),
+            just("VN")
+                .or(just("N"))
+                .ignore_then(radius)
+                .map(Neighbors::Neumann),
+            just('C').ignore_then(offsets).map(Neighbors::custom),
+        ))
+    }
+
     impl Rule {
-        fn parser() -> impl Parser<char, Rule, Error = Simple<char>> {
-            let single_num = text::int(10).map(|s: String| s.parse::<u8>().unwrap());
-            let range = single_num
-                .then(just("-").ignore_then(single_num).or_not())
-                .map(|(low, high)| low..(high.unwrap_or(low) + 1));
-            let ranges_rule = range.separated_by(just(','));
-            let neighbor = choice((
-                just('M').to(Neighbors::Moore),
-                just('N').to(Neighbors::Neumann),
-            ));
-            ranges_rule
+        fn parser(notation: Notation) -> Box<dyn Parser<char, Spanned, Error = Simple<char>>> {
+            match notation {
+                Notation::Standard => Box::new(Self::standard()),
+                Notation::BirthSurvival => Box::new(Self::birth_survival()),
+                Notation::Golly => Box::new(Self::golly()),
+            }
+        }
+
+        /// `survival/birth/states/neighbors`, e.g. `4/4/5/M`.
+        fn standard() -> impl Parser<char, Spanned, Error = Simple<char>> {
+            ranges()
                 .separated_by(just('/'))
                 .exactly(2)
                 .then_ignore(just('/'))
-                .then(single_num)
+                .then(states())
                 .then_ignore(just('/'))
-                .then(neighbor)
+                .then(neighbors())
+                .then_ignore(end())
                 .map(|((ranges, states), neighbors)| {
-                    let survival = ranges[0].clone();
-                    let birth = ranges[1].clone();
-                    Self {
-                        survival,
-                        birth,
+                    let mut ranges = ranges.into_iter();
+                    Spanned {
+                        survival: ranges.next().unwrap_or_default(),
+                        birth: ranges.next().unwrap_or_default(),
                         states,
                         neighbors,
                     }
                 })
         }
+
+        /// `Bbirth/Ssurvival/Cstates/neighbors` in either order of birth and
+        /// survival, e.g. `B5-7/S4-6/C5/M`. The `C` is optional, and states
+        /// and neighbors default to 2 and `M`.
+        fn birth_survival() -> impl Parser<char, Spanned, Error = Simple<char>> {
+            let birth = just('B').ignore_then(ranges());
+            let survival = just('S').ignore_then(ranges());
+            let states = just('/')
+                .ignore_then(just('C').or_not())
+                .ignore_then(states())
+                .or_not()
+                .map_with_span(|states, span: Range<usize>| {
+                    states.unwrap_or((Some(2), span.end..span.end))
+                });
+            let neighbors = just('/')
+                .ignore_then(neighbors())
+                .or_not()
+                .map(|n| n.unwrap_or(Neighbors::Moore(1)));
+            choice((
+                birth.clone().then_ignore(just('/')).then(survival.clone()),
+                survival
+                    .then_ignore(just('/'))
+                    .then(birth)
+                    .map(|(survival, birth)| (birth, survival)),
+            ))
+            .then(states)
+            .then(neighbors)
+            .then_ignore(end())
+            .map(|(((birth, survival), states), neighbors)| Spanned {
+                survival,
+                birth,
+                states,
+                neighbors,
+            })
+        }
+
+        /// Golly's `3Dsurvival/birth` with an optional neighborhood suffix,
+        /// `F` for the 6 faces, `E` for the 12 edges or `C` for the 8
+        /// corners, e.g. `3D4..7/5F`. These rules always have 2 states.
+        fn golly() -> impl Parser<char, Spanned, Error = Simple<char>> {
+            let suffix = choice((
+                just('F').to(Neighbors::Neumann(1)),
+                just('E').to(Neighbors::edges()),
+                just('C').to(Neighbors::corners()),
+            ))
+            .or_not()
+            .map(|n| n.unwrap_or(Neighbors::Moore(1)));
+            just("3D")
+                .ignore_then(ranges())
+                .then_ignore(just('/'))
+                .then(ranges())
+                .then(suffix)
+                .then_ignore(end())
+                .map_with_span(
+                    |((survival, birth), neighbors), span: Range<usize>| Spanned {
+                        survival,
+                        birth,
+                        states: (Some(2), span.end..span.end),
+                        neighbors,
+                    },
+                )
+        }
     }
 
     #[cfg(test)]
     mod tests {
         use super::*;
+        use proptest::prelude::*;
+        use std::ops::Range;
+        use strum::IntoEnumIterator;
 
         #[test]
         fn parse_rule() {
             let input = "4/4/5/M";
-            let rule = Rule::parser().parse(input).unwrap();
+            let rule = input.parse::<Rule>().unwrap();
             assert_eq!(
                 rule,
                 Rule {
                     survival: vec![4..5],
                     birth: vec![4..5],
                     states: 5,
-                    neighbors: Neighbors::Moore
+                    neighbors: Neighbors::Moore(1)
                 }
             );
             let input = "9-26/5-7,12-13,15/5/M";
-            let rule = Rule::parser().parse(input).unwrap();
+            let rule = input.parse::<Rule>().unwrap();
             assert_eq!(
                 rule,
                 Rule {
                     survival: vec![9..27],
                     birth: vec![5..8, 12..14, 15..16],
                     states: 5,
-                    neighbors: Neighbors::Moore
+                    neighbors: Neighbors::Moore(1)
                 }
             );
+            let input = "100-200/80,300/10/N6";
+            let rule = input.parse::<Rule>().unwrap();
+            assert_eq!(
+                rule,
+                Rule {
+                    survival: vec![100..201],
+                    birth: vec![80..81, 300..301],
+                    states: 10,
+                    neighbors: Neighbors::Neumann(6)
+                }
+            );
+            let input = "4/4/5/M2";
+            let rule = input.parse::<Rule>().unwrap();
+            assert_eq!(rule.neighbors, Neighbors::Moore(2));
+            let input = "1/1/2/C(0,0,1)(-1,2,0)(1,0,0)";
+            let rule = input.parse::<Rule>().unwrap();
+            assert_eq!(
+                rule.neighbors,
+                Neighbors::Custom(vec![[-1, 2, 0], [0, 0, 1], [1, 0, 0]])
+            );
+            assert_eq!(rule.neighbors.radius(), 2);
+        }
+
+        #[test]
+        fn neighbors_display() {
+            for n in [
+                Neighbors::Moore(1),
+                Neighbors::Moore(3),
+                Neighbors::Neumann(1),
+                Neighbors::Neumann(2),
+                Neighbors::face_edge(),
+                Neighbors::corners(),
+            ] {
+                let input = format!("4/4/5/{n}");
+                assert_eq!(input.parse::<Rule>().unwrap().neighbors, n);
+            }
+            assert_eq!(
+                Neighbors::custom([[0, 0, -1], [0, 0, 0], [2, 0, 0], [0, 0, -1]]).to_string(),
+                "C(0,0,-1)(2,0,0)"
+            );
+        }
+
+        #[test]
+        fn rule_display() {
+            let rule = Rule {
+                survival: vec![5..6, 4..5, 9..12, 10..11, 20..20],
+                birth: vec![],
+                states: 5,
+                neighbors: Neighbors::Neumann(2),
+            };
+            assert_eq!(rule.to_string(), "4-5,9-11//5/N2");
+            let rule = "13-26/13-14,17-19/2/M".parse::<Rule>().unwrap();
+            assert_eq!(rule.to_string(), "13-26/13-14,17-19/2/M");
+            // parsing merges too, so equal rules compare equal
+            assert_eq!(
+                "4-5/4/5/M".parse::<Rule>().unwrap(),
+                "4,5/4-4/5/M".parse::<Rule>().unwrap()
+            );
+        }
+
+        /// Ranges that a neighborhood of `max` cells can match.
+        fn ranges(max: u16) -> impl Strategy<Value = Vec<Range<u16>>> {
+            prop::collection::vec((0..=max, 0u16..30), 0..6)
+                .prop_map(|v| v.into_iter().map(|(a, len)| a..a + len).collect())
+        }
+
+        fn rules() -> impl Strategy<Value = Rule> {
+            (neighbors(), 2u8..=255).prop_flat_map(|(neighbors, states)| {
+                let max = neighbors.count() as u16;
+                (ranges(max), ranges(max)).prop_map(move |(survival, birth)| Rule {
+                    survival,
+                    birth,
+                    states,
+                    neighbors: neighbors.clone(),
+                })
+            })
+        }
+
+        fn neighbors() -> impl Strategy<Value = Neighbors> {
+            prop_oneof![
+                (0u8..=12).prop_map(Neighbors::Moore),
+                (0u8..=12).prop_map(Neighbors::Neumann),
+                prop::collection::vec(prop::array::uniform3(-127i8..=127), 0..8)
+                    .prop_map(Neighbors::custom),
+            ]
+        }
+
+        proptest! {
+            #[test]
+            fn display_round_trips(rule in rules()) {
+                let parsed = rule.to_string().parse::<Rule>().unwrap();
+                prop_assert_eq!(&parsed.survival, &merge_ranges(&rule.survival));
+                prop_assert_eq!(&parsed.birth, &merge_ranges(&rule.birth));
+                prop_assert_eq!(parsed.to_string(), rule.to_string());
+                prop_assert_eq!(&parsed.to_string().parse::<Rule>().unwrap(), &parsed);
+                for notation in Notation::iter() {
+                    if let Some(s) = parsed.notated(notation).map(|n| n.to_string()) {
+                        prop_assert_eq!(Notation::detect(&s), notation);
+                        prop_assert_eq!(&s.parse::<Rule>().unwrap(), &parsed);
+                    }
+                }
+                for n in 0..1100 {
+                    prop_assert_eq!(rule.passes_survive(n), parsed.passes_survive(n));
+                    prop_assert_eq!(rule.passes_birth(n), parsed.passes_birth(n));
+                }
+            }
+        }
+
+        #[test]
+        fn notations() {
+            let standard = "4-6/5-7/5/M".parse::<Rule>().unwrap();
+            for s in [
+                "B5-7/S4-6/C5/M",
+                "S4-6/B5-7/C5/M",
+                "B5-7/S4-6/5/NN",
+                "S4..6/B5..7/5/M",
+            ] {
+                assert_eq!(Notation::detect(s), Notation::BirthSurvival);
+                assert_eq!(s.parse::<Rule>().unwrap(), standard, "{s}");
+            }
+            assert_eq!(
+                "B4/S4".parse::<Rule>().unwrap(),
+                "4/4/2/M".parse::<Rule>().unwrap()
+            );
+            assert_eq!(
+                "2/3/4/VN2".parse::<Rule>().unwrap(),
+                "2/3/4/N2".parse::<Rule>().unwrap()
+            );
+            for (golly, standard) in [
+                ("3D4..7/5", "4-7/5/2/M"),
+                ("3D4,6/1..3F", "4,6/1-3/2/N"),
+                ("3D/2E", "/2/2/C(-1,-1,0)(-1,0,-1)(-1,0,1)(-1,1,0)(0,-1,-1)(0,-1,1)(0,1,-1)(0,1,1)(1,-1,0)(1,0,-1)(1,0,1)(1,1,0)"),
+                ("3D0-8/3C", "0-8/3/2/C(-1,-1,-1)(-1,-1,1)(-1,1,-1)(-1,1,1)(1,-1,-1)(1,-1,1)(1,1,-1)(1,1,1)"),
+            ] {
+                assert_eq!(Notation::detect(golly), Notation::Golly);
+                let rule = golly.parse::<Rule>().unwrap();
+                assert_eq!(rule, standard.parse::<Rule>().unwrap(), "{golly}");
+                assert_eq!(
+                    rule.notated(Notation::Golly).unwrap().to_string(),
+                    golly.replace('-', "..")
+                );
+            }
+
+            assert_eq!(
+                standard
+                    .notated(Notation::BirthSurvival)
+                    .unwrap()
+                    .to_string(),
+                "B5-7/S4-6/C5/M"
+            );
+            // Golly rules have two states and fixed neighborhoods
+            assert!(standard.notated(Notation::Golly).is_none());
+            assert!("4/4/2/M2"
+                .parse::<Rule>()
+                .unwrap()
+                .notated(Notation::Golly)
+                .is_none());
+            // errors point into the notation that was detected
+            assert_eq!(
+                "B4/S30/C5/M".parse::<Rule>().unwrap_err().diagnostics[0].span,
+                4..6
+            );
+        }
+
+        #[test]
+        fn parse_errors() {
+            fn errors(input: &str) -> Vec<(&str, String)> {
+                input
+                    .parse::<Rule>()
+                    .unwrap_err()
+                    .diagnostics
+                    .into_iter()
+                    .map(|d| (&input[d.span], d.message))
+                    .collect()
+            }
+            assert_eq!(
+                errors("4/4/300/M"),
+                [("300", "the number of states can be at most 255".into())]
+            );
+            assert_eq!(
+                errors("7-3/99999/1/M300"),
+                [
+                    ("7-3", "range is backwards, did you mean 3-7?".into()),
+                    ("99999", "a neighbor count can be at most 65534".into()),
+                    ("1", "a rule needs at least 2 states".into()),
+                    ("300", "a radius can be at most 127".into()),
+                ]
+            );
+            assert_eq!(
+                errors("2/27,3/5/M"),
+                [(
+                    "27",
+                    "M has only 26 neighbors, so this can never match".into()
+                )]
+            );
+            assert_eq!(
+                errors("1/1/2/C(0,-128,1)"),
+                [("-128", "offsets must be between -127 and 127".into())]
+            );
+            assert_eq!(
+                errors("4/4/5/X"),
+                [("X", "unexpected 'X', expected 'C', 'M', 'N' or 'V'".into())]
+            );
+            assert_eq!(
+                errors("4/4/5/M2x"),
+                [("x", "unexpected 'x', expected end of input".into())]
+            );
+            assert_eq!(
+                "4/4/300/M".parse::<Rule>().unwrap_err().to_string(),
+                "4/4/300/M\n    ^^^ the number of states can be at most 255"
+            );
+        }
+
+        #[test]
+        fn neighborhood_presets() {
+            assert_eq!(Neighbors::face_edge().offsets().len(), 18);
+            assert_eq!(Neighbors::corners().offsets().len(), 8);
+            assert_eq!(Neighbors::Neumann(2).offsets().len(), 24);
+            // corners of a large radius add up past i8
+            assert_eq!(Neighbors::Neumann(60).offsets().len(), 295_360);
+            for r in [0, 1, 2, 5, 60] {
+                for n in [Neighbors::Moore(r), Neighbors::Neumann(r)] {
+                    assert_eq!(n.count(), n.offsets().len(), "{n}");
+                }
+            }
+            assert_eq!(Neighbors::Moore(MAX_RADIUS).count(), 255usize.pow(3) - 1);
+            assert!(Neighbors::Neumann(60).offsets().iter().all(|o| o
+                .iter()
+                .map(|c| i16::from(*c).abs())
+                .sum::<i16>()
+                <= 60));
+            assert_eq!(
+                "4/4/5/N50".parse::<Rule>().map(|r| r.neighbors),
+                Ok(Neighbors::Neumann(50))
+            );
+            assert!("4/4/5/M127".parse::<Rule>().is_ok());
+            assert!("4/4/5/M200".parse::<Rule>().is_err());
         }
     }
 }
@@ -0,0 +1,442 @@
+//! Initial states built from layers of simple generators, as an alternative
+//! to the noise core of [`Grid::new_noise`].
+
+use crate::{
+    cycle::mix,
+    grid::{Grid, PackedCell},
+    symmetry::Symmetry,
+};
+#[cfg(feature = "viewer")]
+use bevy::prelude::*;
+use itertools::iproduct;
+use noise::{Fbm, MultiFractal, NoiseFn, OpenSimplex, Perlin, Value, Worley};
+use rayon::prelude::*;
+use strum::{Display, EnumIter};
+
+/// The kinds of coherent noise a [`Generator::Noise`] layer can use.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+pub enum NoiseKind {
+    #[default]
+    OpenSimplex,
+    Perlin,
+    /// Several octaves of Perlin noise summed together.
+    Fbm,
+    Worley,
+    Value,
+}
+
+/// Whether a generator covers the cell at a point.
+type Sampler = Box<dyn Fn([usize; 3]) -> bool>;
+
+/// How one layer decides which cells it covers. Shapes are centered in the
+/// grid.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Generator {
+    /// Every cell independently, with probability `density`.
+    Random { density: f64 },
+    /// Cells where noise sampled at `frequency` times their coordinates is
+    /// above `threshold`. `octaves` only applies to [`NoiseKind::Fbm`].
+    Noise {
+        kind: NoiseKind,
+        frequency: f64,
+        octaves: usize,
+        threshold: f64,
+    },
+    /// A cube `size` cells across, or only its outer layer of cells if
+    /// `hollow`.
+    Cube { size: usize, hollow: bool },
+    /// The cells closer to the center than `radius`, or only the outermost
+    /// of them if `hollow`.
+    Sphere { radius: f64, hollow: bool },
+    /// The one cell in the middle.
+    SingleCell,
+    /// Random cells with probability `density` within `radius` of the center,
+    /// mirrored across the middle of every axis.
+    Symmetric { density: f64, radius: f64 },
+}
+
+impl Generator {
+    /// A short name for the kind of generator.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Random { .. } => "Random",
+            Self::Noise { .. } => "Noise",
+            Self::Cube { .. } => "Cube",
+            Self::Sphere { .. } => "Sphere",
+            Self::SingleCell => "Single cell",
+            Self::Symmetric { .. } => "Symmetric",
+        }
+    }
+
+    /// Whether the layer's seed changes which cells it covers.
+    pub fn is_random(&self) -> bool {
+        match self {
+            Self::Random { .. } | Self::Noise { .. } | Self::Symmetric { .. } => true,
+            Self::Cube { .. } | Self::Sphere { .. } | Self::SingleCell => false,
+        }
+    }
+
+    /// One generator of each kind, with typical parameters.
+    pub fn defaults() -> [Generator; 6] {
+        [
+            Self::Random { density: 0.3 },
+            Self::Noise {
+                kind: NoiseKind::default(),
+                frequency: 0.1,
+                octaves: 4,
+                threshold: 0.1,
+            },
+            Self::Cube {
+                size: 10,
+                hollow: false,
+            },
+            Self::Sphere {
+                radius: 10.,
+                hollow: false,
+            },
+            Self::SingleCell,
+            Self::Symmetric {
+                density: 0.3,
+                radius: 6.,
+            },
+        ]
+    }
+
+    /// Whether the cell at `p` is covered, for a grid of `dims` cells.
+    fn sampler(&self, dims: [usize; 3], seed: u32) -> Sampler {
+        let center = dims.map(|d| d / 2);
+        let dist = move |p: [usize; 3]| {
+            (0..3)
+                .map(|d| (p[d] as f32 - center[d] as f32).powi(2))
+                .sum::<f32>()
+                .sqrt()
+        };
+        // a number in 0..1 that's random but always the same for `p`
+        let chance = move |p: [usize; 3]| {
+            let h = p.iter().fold(u64::from(seed), |h, c| mix(h ^ *c as u64));
+            mix(h) as f64 / u64::MAX as f64
+        };
+        match *self {
+            Self::Random { density } => Box::new(move |p| chance(p) < density),
+            Self::Noise {
+                kind,
+                frequency,
+                octaves,
+                threshold,
+            } => {
+                let noise: Box<dyn NoiseFn<f64, 3>> = match kind {
+                    NoiseKind::OpenSimplex => Box::new(OpenSimplex::new(seed)),
+                    NoiseKind::Perlin => Box::new(Perlin::new(seed)),
+                    NoiseKind::Fbm => Box::new(Fbm::<Perlin>::new(seed).set_octaves(octaves)),
+                    NoiseKind::Worley => Box::new(Worley::new(seed)),
+                    NoiseKind::Value => Box::new(Value::new(seed)),
+                };
+                Box::new(move |p| noise.get(p.map(|c| c as f64 * frequency)) > threshold)
+            }
+            Self::Cube { size, hollow } => Box::new(move |p| {
+                // offsets from the cube's first corner
+                let o = (0..3).map(|d| (p[d] + size / 2).checked_sub(center[d]));
+                let mut on_surface = false;
+                for o in o {
+                    match o {
+                        Some(o) if o < size => on_surface |= o == 0 || o == size - 1,
+                        _ => return false,
+                    }
+                }
+                !hollow || on_surface
+            }),
+            Self::Sphere { radius, hollow } => {
+                let radius = radius as f32;
+                Box::new(move |p| {
+                    let r = dist(p);
+                    r < radius && (!hollow || r >= radius - 1.)
+                })
+            }
+            Self::SingleCell => Box::new(move |p| p == center),
+            Self::Symmetric { density, radius } => Box::new(move |p| {
+                // measured from the exact middle, which is between two cells
+                // on even axes, so mirrored cells are the same distance away
+                let r = (0..3)
+                    .map(|d| (p[d] as f64 - (dims[d] - 1) as f64 / 2.).powi(2))
+                    .sum::<f64>()
+                    .sqrt();
+                let mirrored = [0, 1, 2].map(|d| p[d].min(dims[d] - 1 - p[d]));
+                r < radius && chance(mirrored) < density
+            }),
+        }
+    }
+}
+
+/// How a layer combines with the layers below it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+pub enum Blend {
+    /// Brings the covered cells to life.
+    #[default]
+    Add,
+    /// Kills the covered cells.
+    Subtract,
+    /// Kills the cells that aren't covered.
+    Intersect,
+}
+
+/// One generator in a [`Seed`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub generator: Generator,
+    /// Seeds the random and noise generators, so the same layer always
+    /// covers the same cells.
+    pub seed: u32,
+    pub blend: Blend,
+}
+
+/// A stack of layers applied in order to an empty grid.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "viewer", derive(Resource))]
+pub struct Seed {
+    pub layers: Vec<Layer>,
+}
+
+impl Default for Seed {
+    /// A noise ball like the default [`Grid::new_noise`], but with smoother
+    /// noise.
+    fn default() -> Self {
+        Self {
+            layers: vec![
+                Layer {
+                    generator: Generator::defaults()[1].clone(),
+                    seed: 1,
+                    blend: Blend::Add,
+                },
+                Layer {
+                    generator: Generator::defaults()[3].clone(),
+                    seed: 1,
+                    blend: Blend::Intersect,
+                },
+            ],
+        }
+    }
+}
+
+impl Seed {
+    /// Moves every layer on to its next seed. Returns whether that changes
+    /// which cells any of them cover.
+    pub fn reseed(&mut self) -> bool {
+        for layer in &mut self.layers {
+            layer.seed = layer.seed.wrapping_add(1);
+        }
+        self.layers.iter().any(|l| l.generator.is_random())
+    }
+
+    /// A grid of `dims` cells with the layers applied.
+    pub fn generate(&self, dims: [usize; 3]) -> Grid {
+        // some noise functions can't be shared between threads, so each
+        // thread builds its own
+        let samplers = || {
+            self.layers
+                .iter()
+                .map(|l| (l.blend, l.generator.sampler(dims, l.seed)))
+                .collect::<Vec<_>>()
+        };
+        let [_, h, d] = dims;
+        let cells = (0..dims.iter().product())
+            .into_par_iter()
+            .map_init(samplers, |samplers, i: usize| {
+                let p = [i / (h * d), i / d % h, i % d];
+                if alive(samplers, p) {
+                    PackedCell::ALIVE
+                } else {
+                    PackedCell::DEAD
+                }
+            })
+            .collect();
+        Grid::from_packed(dims, cells)
+    }
+
+    /// Which cells of the `z` layer of a grid of `dims` cells start alive
+    /// with `symmetry`, row by row, without seeding the whole grid.
+    pub fn slice(&self, dims: [usize; 3], z: usize, symmetry: Symmetry) -> Vec<bool> {
+        let samplers = self
+            .layers
+            .iter()
+            .map(|l| (l.blend, l.generator.sampler(dims, l.seed)))
+            .collect::<Vec<_>>();
+        let source = symmetry.source(dims);
+        iproduct!(0..dims[1], 0..dims[0])
+            .map(|(y, x)| alive(&samplers, source([x, y, z])))
+            .collect()
+    }
+}
+
+/// Applies each layer's blend to the cell at `p`.
+fn alive(samplers: &[(Blend, Sampler)], p: [usize; 3]) -> bool {
+    samplers
+        .iter()
+        .fold(false, |alive, (blend, covers)| match blend {
+            Blend::Add => alive || covers(p),
+            Blend::Subtract => alive && !covers(p),
+            Blend::Intersect => alive && covers(p),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cell::CellStatus,
+        grid::{NoiseSettings, Point},
+    };
+
+    fn layer(generator: Generator, seed: u32, blend: Blend) -> Layer {
+        Layer {
+            generator,
+            seed,
+            blend,
+        }
+    }
+
+    fn alive(g: &Grid) -> usize {
+        g.iter().filter(|(_, c)| *c == CellStatus::Alive).count()
+    }
+
+    #[test]
+    fn shapes() {
+        let count = |generator| {
+            alive(
+                &Seed {
+                    layers: vec![layer(generator, 0, Blend::Add)],
+                }
+                .generate([20, 21, 22]),
+            )
+        };
+        let cube = |size, hollow| Generator::Cube { size, hollow };
+        assert_eq!(count(cube(6, false)), 6 * 6 * 6);
+        assert_eq!(count(cube(6, true)), 6 * 6 * 6 - 4 * 4 * 4);
+        assert_eq!(count(cube(1, true)), 1);
+        assert_eq!(count(cube(30, false)), 20 * 21 * 22);
+        let sphere = |radius, hollow| Generator::Sphere { radius, hollow };
+        let (solid, inside) = (count(sphere(5., false)), count(sphere(4., false)));
+        assert_eq!(count(sphere(5., true)), solid - inside);
+        assert_eq!(count(Generator::SingleCell), 1);
+    }
+
+    #[test]
+    fn random_layers_follow_their_seed() {
+        let seed = |generator: &Generator, seed| {
+            Seed {
+                layers: vec![layer(generator.clone(), seed, Blend::Add)],
+            }
+            .generate([16; 3])
+        };
+        for generator in Generator::defaults() {
+            let g = seed(&generator, 3);
+            assert!(g.iter().eq(seed(&generator, 3).iter()), "{generator:?}");
+        }
+        let random = Generator::Random { density: 0.25 };
+        assert!(!seed(&random, 1).iter().eq(seed(&random, 2).iter()));
+        let n = alive(&seed(&random, 1));
+        assert!((900..1150).contains(&n), "{n} of 4096 cells");
+
+        let mut shapes = Seed {
+            layers: vec![
+                layer(Generator::SingleCell, 1, Blend::Add),
+                layer(
+                    Generator::Cube {
+                        size: 4,
+                        hollow: false,
+                    },
+                    u32::MAX,
+                    Blend::Add,
+                ),
+            ],
+        };
+        assert!(!shapes.reseed());
+        assert_eq!(shapes.layers[1].seed, 0);
+        shapes.layers.push(layer(random, 1, Blend::Add));
+        assert!(shapes.reseed());
+        assert_eq!(shapes.layers[2].seed, 2);
+    }
+
+    #[test]
+    fn layers_combine() {
+        // noise intersected with a sphere is the classic noise core
+        let n = NoiseSettings::default();
+        let core = Seed {
+            layers: vec![
+                layer(
+                    Generator::Noise {
+                        kind: NoiseKind::OpenSimplex,
+                        frequency: 1.,
+                        octaves: 1,
+                        threshold: n.threshold,
+                    },
+                    n.seed,
+                    Blend::Add,
+                ),
+                layer(
+                    Generator::Sphere {
+                        radius: n.size.into(),
+                        hollow: false,
+                    },
+                    0,
+                    Blend::Intersect,
+                ),
+            ],
+        };
+        let dims = [30, 24, 27];
+        assert!(core
+            .generate(dims)
+            .iter()
+            .eq(Grid::new_noise(dims, &n).iter()));
+
+        // a cube with its middle cell taken out
+        let hollowed = Seed {
+            layers: vec![
+                layer(
+                    Generator::Cube {
+                        size: 3,
+                        hollow: false,
+                    },
+                    0,
+                    Blend::Add,
+                ),
+                layer(Generator::SingleCell, 0, Blend::Subtract),
+            ],
+        }
+        .generate([9; 3]);
+        assert_eq!(alive(&hollowed), 26);
+        assert_eq!(hollowed.get(&Point::new(4, 4, 4)), Some(CellStatus::Dead));
+
+        // a slice is one layer of the seeded grid
+        let slice = core.slice(dims, 13, Symmetry::None);
+        for (p, c) in core.generate(dims).iter() {
+            let [x, y, z] = <[usize; 3]>::from(p);
+            if z == 13 {
+                assert_eq!(slice[y * dims[0] + x], c == CellStatus::Alive);
+            }
+        }
+    }
+
+    #[test]
+    fn symmetric_seed_is_mirrored() {
+        let dims = [15, 12, 9];
+        let g = Seed {
+            layers: vec![layer(
+                Generator::Symmetric {
+                    density: 0.4,
+                    radius: 6.,
+                },
+                5,
+                Blend::Add,
+            )],
+        }
+        .generate(dims);
+        assert!(alive(&g) > 0);
+        for (p, c) in g.iter() {
+            let [x, y, z] = <[usize; 3]>::from(p);
+            let [w, h, d] = dims.map(|d| d - 1);
+            for q in [[w - x, y, z], [x, h - y, z], [x, y, d - z]] {
+                assert_eq!(g.get(&Point::new(q[0], q[1], q[2])), Some(c));
+            }
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! A compact, versioned binary format for saving a [`Grid`] together with
+//! the [`Rule`] it runs and how far it has run.
+//!
+//! All integers are little-endian. A snapshot is laid out as:
+//!
+//! | field        | encoding                                              |
+//! |--------------|-------------------------------------------------------|
+//! | magic        | the bytes `CA3D`                                      |
+//! | version      | `u8`, currently [`VERSION`]                           |
+//! | dimensions   | three `u32`s: width, height, depth                    |
+//! | generation   | `u64`                                                 |
+//! | rule         | survival and birth ranges, states and neighbors       |
+//! | cells        | runs of equal cells in [`Grid`] order                 |
+//!
+//! Survival and birth ranges are each a `u16` count followed by that many
+//! `u16` pairs of start and end. The neighbors are a tag byte, `0` for Moore
+//! and `1` for Neumann followed by the `u8` radius, or `2` for custom followed
+//! by a `u16` count and that many `i8` triples. Each run of cells is the cell
+//! byte (`0` dead, `255` alive, anything else the health of a dying cell)
+//! followed by the run length as an unsigned LEB128 varint.
+
+use crate::{
+    grid::{Grid, PackedCell},
+    rule::{Neighbors, Rule, MAX_RADIUS},
+};
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    ops::Range,
+};
+
+const MAGIC: &[u8; 4] = b"CA3D";
+/// The format version written by [`Snapshot::write`].
+pub const VERSION: u8 = 1;
+/// The most cells a snapshot holds, those of the largest grid the viewer
+/// makes, so a damaged header can't ask for more memory than that.
+pub const MAX_CELLS: usize = 400 * 400 * 400;
+
+/// A grid, the rule it runs and the generation it's at.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub grid: Grid,
+    pub rule: Rule,
+    pub generation: u64,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The data doesn't start with the snapshot magic bytes.
+    NotASnapshot,
+    /// The snapshot was written by a newer, unknown version of the format.
+    UnsupportedVersion(u8),
+    /// The data is a snapshot, but a damaged one.
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::NotASnapshot => write!(f, "not a grid snapshot"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {v}"),
+            Self::Corrupt(what) => write!(f, "corrupt snapshot: {what}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => Self::Corrupt("unexpected end of data"),
+            _ => Self::Io(e),
+        }
+    }
+}
+
+impl Snapshot {
+    /// Writes the snapshot, failing for grids of more than [`MAX_CELLS`]
+    /// cells, which couldn't be read back.
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        if self.grid.cell_count() > MAX_CELLS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "grid too large for a snapshot",
+            ));
+        }
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        for d in self.grid.dims() {
+            let d = u32::try_from(d)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "grid too large"))?;
+            w.write_all(&d.to_le_bytes())?;
+        }
+        w.write_all(&self.generation.to_le_bytes())?;
+        write_rule(w, &self.rule)?;
+
+        let mut cells = self.grid.packed().iter().peekable();
+        while let Some(&cell) = cells.next() {
+            let mut run = 1u64;
+            while cells.next_if_eq(&&cell).is_some() {
+                run += 1;
+            }
+            w.write_all(&[cell.0])?;
+            write_varint(w, run)?;
+        }
+        Ok(())
+    }
+
+    pub fn read(r: &mut impl Read) -> Result<Self, SnapshotError> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if magic != *MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        match read_u8(r)? {
+            VERSION => {}
+            v => return Err(SnapshotError::UnsupportedVersion(v)),
+        }
+        let mut dims = [0; 3];
+        for d in &mut dims {
+            *d = read_u32(r)? as usize;
+        }
+        let generation = read_u64(r)?;
+        let rule = read_rule(r)?;
+
+        let total = dims
+            .iter()
+            .try_fold(1usize, |n, d| n.checked_mul(*d))
+            .filter(|total| *total <= MAX_CELLS)
+            .ok_or(SnapshotError::Corrupt("dimensions too large"))?;
+        let mut cells = Vec::new();
+        while cells.len() < total {
+            let cell = PackedCell(read_u8(r)?);
+            let run = usize::try_from(read_varint(r)?)
+                .ok()
+                .filter(|run| *run > 0 && *run <= total - cells.len())
+                .ok_or(SnapshotError::Corrupt("bad run length"))?;
+            cells.resize(cells.len() + run, cell);
+        }
+        Ok(Self {
+            grid: Grid::from_packed(dims, cells),
+            rule,
+            generation,
+        })
+    }
+}
+
+fn write_rule(w: &mut impl Write, rule: &Rule) -> io::Result<()> {
+    for ranges in [&rule.survival, &rule.birth] {
+        w.write_all(&(ranges.len() as u16).to_le_bytes())?;
+        for r in ranges {
+            w.write_all(&r.start.to_le_bytes())?;
+            w.write_all(&r.end.to_le_bytes())?;
+        }
+    }
+    w.write_all(&[rule.states])?;
+    match &rule.neighbors {
+        Neighbors::Moore(r) => w.write_all(&[0, *r]),
+        Neighbors::Neumann(r) => w.write_all(&[1, *r]),
+        Neighbors::Custom(offsets) => {
+            w.write_all(&[2])?;
+            w.write_all(&(offsets.len() as u16).to_le_bytes())?;
+            for o in offsets {
+                w.write_all(&o.map(|c| c as u8))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn read_rule(r: &mut impl Read) -> Result<Rule, SnapshotError> {
+    let mut ranges = || -> Result<Vec<Range<u16>>, SnapshotError> {
+        (0..read_u16(r)?)
+            .map(|_| Ok(read_u16(r)?..read_u16(r)?))
+            .collect()
+    };
+    let survival = ranges()?;
+    let birth = ranges()?;
+    let states = read_u8(r)?;
+    if states < 2 {
+        return Err(SnapshotError::Corrupt("fewer than two states"));
+    }
+    let radius = |r: &mut _| match read_u8(r)? {
+        radius if radius <= MAX_RADIUS => Ok(radius),
+        _ => Err(SnapshotError::Corrupt("radius too large")),
+    };
+    let neighbors = match read_u8(r)? {
+        0 => Neighbors::Moore(radius(r)?),
+        1 => Neighbors::Neumann(radius(r)?),
+        2 => Neighbors::custom(
+            (0..read_u16(r)?)
+                .map(|_| {
+                    let mut o = [0; 3];
+                    r.read_exact(&mut o)?;
+                    let o = o.map(|c| c as i8);
+                    if o.iter().any(|c| c.unsigned_abs() > MAX_RADIUS) {
+                        return Err(SnapshotError::Corrupt("offset too large"));
+                    }
+                    Ok(o)
+                })
+                .collect::<Result<Vec<_>, SnapshotError>>()?,
+        ),
+        _ => return Err(SnapshotError::Corrupt("unknown neighborhood")),
+    };
+    Ok(Rule {
+        survival,
+        birth,
+        states,
+        neighbors,
+    })
+}
+
+fn write_varint(w: &mut impl Write, mut n: u64) -> io::Result<()> {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(r: &mut impl Read) -> Result<u64, SnapshotError> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(r)?;
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(SnapshotError::Corrupt("run length too long"))
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut b = [0; 1];
+    r.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+fn read_u16(r: &mut impl Read) -> io::Result<u16> {
+    let mut b = [0; 2];
+    r.read_exact(&mut b)?;
+    Ok(u16::from_le_bytes(b))
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut b = [0; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut b = [0; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cell::CellStatus,
+        grid::{Boundary, NoiseSettings, Point},
+    };
+
+    fn round_trip(s: &Snapshot) -> Snapshot {
+        let mut bytes = Vec::new();
+        s.write(&mut bytes).unwrap();
+        Snapshot::read(&mut bytes.as_slice()).unwrap()
+    }
+
+    fn assert_same(a: &Snapshot, b: &Snapshot) {
+        assert_eq!(a.grid.dims(), b.grid.dims());
+        assert!(a.grid.iter().eq(b.grid.iter()));
+        assert_eq!(a.rule, b.rule);
+        assert_eq!(a.generation, b.generation);
+    }
+
+    #[test]
+    fn every_cell_status_round_trips() {
+        let mut grid = Grid::new([4, 3, 70]);
+        let cells = [
+            CellStatus::Alive,
+            CellStatus::Dead,
+            CellStatus::Dying { health: 1 },
+            CellStatus::Dying { health: 7 },
+            CellStatus::Dying { health: 254 },
+        ];
+        for (i, c) in cells.into_iter().cycle().take(40).enumerate() {
+            grid.set(&Point::new(i % 4, i % 3, i), c);
+        }
+        let snapshot = Snapshot {
+            grid,
+            rule: "0,2/1/255/C(0,0,1)(-1,2,-3)".parse().unwrap(),
+            generation: u64::MAX,
+        };
+        let read = round_trip(&snapshot);
+        assert_same(&snapshot, &read);
+        for c in cells {
+            assert!(read.grid.iter().any(|(_, r)| r == c), "{c:?} missing");
+        }
+    }
+
+    #[test]
+    fn stepped_grid_round_trips() {
+        let rule: Rule = "4/4/5/N2".parse().unwrap();
+        let mut grid = Grid::new_noise([30, 20, 10], &NoiseSettings::default());
+        for _ in 0..5 {
+            grid = grid.next(&rule, Boundary::Torus);
+        }
+        let snapshot = Snapshot {
+            grid,
+            rule,
+            generation: 5,
+        };
+        let mut bytes = Vec::new();
+        snapshot.write(&mut bytes).unwrap();
+        // runs of dead cells keep it smaller than a byte per cell
+        assert!(bytes.len() < snapshot.grid.cell_count());
+        let mut empty = Vec::new();
+        Snapshot {
+            grid: Grid::new([100; 3]),
+            ..snapshot.clone()
+        }
+        .write(&mut empty)
+        .unwrap();
+        assert!(empty.len() < 64);
+        assert_same(&snapshot, &round_trip(&snapshot));
+        // the loaded grid keeps stepping like the original
+        let next = |g: &Grid| g.next(&snapshot.rule, Boundary::Torus);
+        assert!(next(&snapshot.grid)
+            .iter()
+            .eq(next(&round_trip(&snapshot).grid).iter()));
+    }
+
+    #[test]
+    fn rejects_bad_data() {
+        let snapshot = Snapshot {
+            grid: Grid::new([2, 2, 2]),
+            rule: "4/4/5/M".parse().unwrap(),
+            generation: 0,
+        };
+        let mut bytes = Vec::new();
+        snapshot.write(&mut bytes).unwrap();
+
+        let read = |bytes: &[u8]| Snapshot::read(&mut &*bytes).map(|_| ());
+        assert!(matches!(read(b"PNG?"), Err(SnapshotError::NotASnapshot)));
+        let mut newer = bytes.clone();
+        newer[4] = VERSION + 1;
+        assert!(matches!(
+            read(&newer),
+            Err(SnapshotError::UnsupportedVersion(_))
+        ));
+        for len in 0..bytes.len() {
+            assert!(read(&bytes[..len]).is_err(), "truncated to {len}");
+        }
+        // a radius too large to step, after the header and the two ranges
+        let mut wide = bytes.clone();
+        assert_eq!(wide[38..40], [0, 1]);
+        wide[39] = 200;
+        assert!(matches!(read(&wide), Err(SnapshotError::Corrupt(_))));
+        // a run longer than the grid
+        let mut long = bytes.clone();
+        *long.last_mut().unwrap() = 9;
+        assert!(matches!(read(&long), Err(SnapshotError::Corrupt(_))));
+        // a huge grid with a single run, which mustn't be allocated
+        let mut huge = bytes.clone();
+        huge[5..17].copy_from_slice(&[0, 0, 16, 0, 0, 0, 16, 0, 1, 0, 0, 0]);
+        let runs = huge.len() - 2;
+        huge.truncate(runs);
+        huge.extend([0, 0x80, 0x80, 0x80, 0x80, 0x80, 0x20]);
+        assert!(matches!(read(&huge), Err(SnapshotError::Corrupt(_))));
+        // a custom neighborhood offset past the largest radius
+        let custom = Snapshot {
+            rule: "1/1/2/C(1,0,0)".parse().unwrap(),
+            ..snapshot.clone()
+        };
+        let mut far = Vec::new();
+        custom.write(&mut far).unwrap();
+        assert_eq!(far[38..42], [2, 1, 0, 1]);
+        far[41] = 0x80;
+        assert!(matches!(read(&far), Err(SnapshotError::Corrupt(_))));
+        // and such grids aren't written in the first place
+        let too_large = Snapshot {
+            grid: Grid::new([401, 400, 400]),
+            ..snapshot
+        };
+        assert!(too_large.write(&mut Vec::new()).is_err());
+        assert!(read(&bytes).is_ok());
+    }
+}
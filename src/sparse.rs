@@ -0,0 +1,226 @@
+//! An unbounded universe that only stores the chunks cells actually occupy.
+
+use crate::{
+    cell::CellStatus,
+    grid::{Grid, PackedCell},
+    neighborhood::{NeighborCounts, Shape},
+    rule::Rule,
+};
+#[cfg(feature = "viewer")]
+use bevy::prelude::*;
+use itertools::iproduct;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Side length of the cubic chunks a [`SparseGrid`] is allocated in.
+const CHUNK: i64 = 16;
+const CHUNK_CELLS: usize = (CHUNK * CHUNK * CHUNK) as usize;
+
+/// A grid without edges, stored as a hash map of fixed-size chunks.
+///
+/// Chunks are allocated as cells are born in them and freed once every cell
+/// in them is dead, so structures can grow indefinitely in any direction
+/// while memory and step time follow the cells that are actually there.
+///
+/// Only chunks within reach of an occupied chunk are stepped, so rules that
+/// give birth to cells with no live neighbors only fill the space around
+/// existing cells rather than all of it.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "viewer", derive(Component))]
+pub struct SparseGrid {
+    /// Cells of each occupied chunk, laid out like a [`Grid`] of side
+    /// [`CHUNK`].
+    chunks: HashMap<[i64; 3], Box<[PackedCell]>>,
+}
+
+/// The chunk containing `p`, and the index of `p` within it.
+fn locate(p: [i64; 3]) -> ([i64; 3], usize) {
+    let [x, y, z] = p.map(|c| c.rem_euclid(CHUNK) as usize);
+    let c = CHUNK as usize;
+    (p.map(|c| c.div_euclid(CHUNK)), (x * c + y) * c + z)
+}
+
+impl SparseGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, p: [i64; 3]) -> CellStatus {
+        let (key, i) = locate(p);
+        self.chunks
+            .get(&key)
+            .map_or(CellStatus::Dead, |cells| cells[i].into())
+    }
+
+    pub fn set(&mut self, p: [i64; 3], c: CellStatus) {
+        let (key, i) = locate(p);
+        if c == CellStatus::Dead {
+            if let Some(cells) = self.chunks.get_mut(&key) {
+                cells[i] = PackedCell::DEAD;
+                if cells.iter().all(|c| *c == PackedCell::DEAD) {
+                    self.chunks.remove(&key);
+                }
+            }
+        } else {
+            self.chunks
+                .entry(key)
+                .or_insert_with(|| vec![PackedCell::DEAD; CHUNK_CELLS].into())[i] = c.into();
+        }
+    }
+
+    /// Steps every cell to the next generation, spread over all cores.
+    pub fn next(&self, rule: &Rule) -> SparseGrid {
+        let shape = Shape::new(&rule.neighbors);
+        let reach = (shape.radius() as i64 + CHUNK - 1) / CHUNK;
+        let side = 2 * reach + 1;
+        let mut keys = self
+            .chunks
+            .keys()
+            .flat_map(|k| {
+                iproduct!(-reach..=reach, -reach..=reach, -reach..=reach)
+                    .map(move |(x, y, z)| [k[0] + x, k[1] + y, k[2] + z])
+            })
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+
+        let chunks = keys
+            .par_iter()
+            .filter_map(|&key| {
+                // the chunks within reach, so cells don't each need a lookup
+                let near = iproduct!(-reach..=reach, -reach..=reach, -reach..=reach)
+                    .map(|(x, y, z)| self.chunks.get(&[key[0] + x, key[1] + y, key[2] + z]))
+                    .collect::<Vec<_>>();
+                let counts = NeighborCounts::new(&shape, [CHUNK as usize; 3], |p| {
+                    let ([cx, cy, cz], i) = locate(p.map(|c| c as i64));
+                    let chunk = ((cx + reach) * side + cy + reach) * side + cz + reach;
+                    near[chunk as usize].is_some_and(|cells| cells[i] == PackedCell::ALIVE)
+                });
+                let own = self.chunks.get(&key);
+                let c = CHUNK as usize;
+                let cells = iproduct!(0..c, 0..c, 0..c)
+                    .enumerate()
+                    .map(|(i, (x, y, z))| {
+                        let cell = own.map_or(CellStatus::Dead, |cells| cells[i].into());
+                        PackedCell::from(cell.next_state(rule, counts.get(x, y, z)))
+                    })
+                    .collect::<Box<[_]>>();
+                cells
+                    .iter()
+                    .any(|c| *c != PackedCell::DEAD)
+                    .then_some((key, cells))
+            })
+            .collect();
+        Self { chunks }
+    }
+
+    /// Every cell that isn't dead, with its position.
+    pub fn iter(&self) -> impl Iterator<Item = ([i64; 3], CellStatus)> + '_ {
+        self.chunks.iter().flat_map(|(key, cells)| {
+            let origin = key.map(|c| c * CHUNK);
+            iproduct!(0..CHUNK, 0..CHUNK, 0..CHUNK)
+                .zip(cells.iter())
+                .filter(|(_, c)| **c != PackedCell::DEAD)
+                .map(move |((x, y, z), c)| {
+                    ([origin[0] + x, origin[1] + y, origin[2] + z], (*c).into())
+                })
+        })
+    }
+
+    /// The smallest and largest coordinates of any cell that isn't dead, or
+    /// `None` if every cell is.
+    pub fn bounds(&self) -> Option<([i64; 3], [i64; 3])> {
+        self.iter().fold(None, |bounds, (p, _)| {
+            let (lo, hi) = bounds.unwrap_or((p, p));
+            Some((
+                [0, 1, 2].map(|d| lo[d].min(p[d])),
+                [0, 1, 2].map(|d| hi[d].max(p[d])),
+            ))
+        })
+    }
+
+    /// Number of allocated chunks.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+impl From<&Grid> for SparseGrid {
+    /// Copies the cells of `grid`, keeping their coordinates.
+    fn from(grid: &Grid) -> Self {
+        let mut sparse = Self::new();
+        for (p, c) in grid.iter().filter(|(_, c)| *c != CellStatus::Dead) {
+            sparse.set(<[usize; 3]>::from(p).map(|c| c as i64), c);
+        }
+        sparse
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::{Boundary, NoiseSettings},
+        rule::Neighbors,
+    };
+
+    #[test]
+    fn matches_bounded_grid_away_from_edges() {
+        let rule: Rule = "4/4/5/M".parse().unwrap();
+        let mut grid = Grid::new_noise([48; 3], &NoiseSettings::default());
+        let mut sparse = SparseGrid::from(&grid);
+        // growth is at most one cell per generation, so the walls are never
+        // reached from a core of radius 10 in the middle
+        for _ in 0..8 {
+            grid = grid.next(&rule, Boundary::DeadWall);
+            sparse = sparse.next(&rule);
+            for (p, c) in grid.iter() {
+                let [x, y, z] = <[usize; 3]>::from(p).map(|c| c as i64);
+                assert_eq!(sparse.get([x, y, z]), c);
+            }
+            assert_eq!(
+                sparse.iter().count(),
+                grid.iter().filter(|(_, c)| *c != CellStatus::Dead).count()
+            );
+        }
+    }
+
+    #[test]
+    fn grows_and_shrinks_across_chunks() {
+        let mut sparse = SparseGrid::new();
+        sparse.set([0, 0, 0], CellStatus::Alive);
+        assert_eq!(sparse.chunk_count(), 1);
+
+        // every cell next to a live one is born: a cube growing by one cell
+        // per generation in every direction, into negative coordinates
+        let grow = Rule {
+            survival: vec![0..27],
+            birth: vec![1..27],
+            states: 2,
+            neighbors: Neighbors::Moore(1),
+        };
+        for _ in 0..20 {
+            sparse = sparse.next(&grow);
+        }
+        assert_eq!(sparse.bounds(), Some(([-20; 3], [20; 3])));
+        assert_eq!(sparse.iter().count(), 41 * 41 * 41);
+        assert_eq!(sparse.get([-20, 20, 0]), CellStatus::Alive);
+        assert_eq!(sparse.get([-21, 0, 0]), CellStatus::Dead);
+        // -20..=20 touches chunks -2..=1 on each axis
+        assert_eq!(sparse.chunk_count(), 4 * 4 * 4);
+
+        // nothing survives, and the empty chunks are freed
+        let die = Rule {
+            survival: vec![],
+            birth: vec![],
+            ..grow
+        };
+        sparse = sparse.next(&die);
+        assert_eq!(sparse.bounds(), None);
+        assert_eq!(sparse.chunk_count(), 0);
+
+        sparse.set([5, -5, 100], CellStatus::Alive);
+        sparse.set([5, -5, 100], CellStatus::Dead);
+        assert_eq!(sparse.chunk_count(), 0);
+    }
+}
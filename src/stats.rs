@@ -0,0 +1,198 @@
+//! Population statistics of a single generation, and a CSV format for a
+//! series of them.
+
+use crate::{automaton::Automaton, cell::CellStatus, cycle::cell_hash};
+use std::io::{self, Write};
+
+/// What a generation of an [`Automaton`] looks like, and how it differs from
+/// the one before.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub alive: usize,
+    /// Dying cells by health: `dying[0]` counts those with a health of 1,
+    /// which die next generation.
+    pub dying: Vec<usize>,
+    /// Cells that are alive but weren't in the previous generation.
+    pub births: usize,
+    /// Cells that were alive in the previous generation but aren't any more.
+    pub deaths: usize,
+    /// The smallest and largest coordinates of the alive cells.
+    pub bounds: Option<([i64; 3], [i64; 3])>,
+    /// The mean position of the alive cells.
+    pub center_of_mass: Option<[f64; 3]>,
+    /// A hash of every cell that isn't dead, the same for identical
+    /// generations. See [`CycleDetector`](crate::cycle::CycleDetector).
+    pub fingerprint: u64,
+}
+
+impl Stats {
+    /// Measures `g`, a universe running a rule with `states` states. Births
+    /// and deaths are counted against `previous`, and are zero without it.
+    pub fn new<G: Automaton>(g: &G, previous: Option<&G>, states: u8) -> Self {
+        let mut stats = Self {
+            alive: 0,
+            dying: vec![0; usize::from(states.saturating_sub(2))],
+            births: 0,
+            deaths: 0,
+            bounds: None,
+            center_of_mass: None,
+            fingerprint: 0,
+        };
+        let mut survivors = 0;
+        let mut sum = [0.; 3];
+        for (p, c) in g.live_cells() {
+            stats.fingerprint = stats.fingerprint.wrapping_add(cell_hash(p, c));
+            match c {
+                CellStatus::Alive => {
+                    stats.alive += 1;
+                    for (s, c) in sum.iter_mut().zip(p) {
+                        *s += c as f64;
+                    }
+                    let (lo, hi) = stats.bounds.unwrap_or((p, p));
+                    stats.bounds = Some((
+                        [0, 1, 2].map(|d| lo[d].min(p[d])),
+                        [0, 1, 2].map(|d| hi[d].max(p[d])),
+                    ));
+                    if previous.is_some_and(|g| g.status(p) == CellStatus::Alive) {
+                        survivors += 1;
+                    }
+                }
+                CellStatus::Dying { health } => {
+                    let i = usize::from(health) - 1;
+                    if i >= stats.dying.len() {
+                        stats.dying.resize(i + 1, 0);
+                    }
+                    stats.dying[i] += 1;
+                }
+                CellStatus::Dead => {}
+            }
+        }
+        if let Some(previous) = previous {
+            let alive_before = previous
+                .live_cells()
+                .filter(|(_, c)| *c == CellStatus::Alive)
+                .count();
+            stats.births = stats.alive - survivors;
+            stats.deaths = alive_before - survivors;
+        }
+        if stats.alive > 0 {
+            stats.center_of_mass = Some(sum.map(|s| s / stats.alive as f64));
+        }
+        stats
+    }
+
+    /// Number of dying cells of any health.
+    pub fn dying_total(&self) -> usize {
+        self.dying.iter().sum()
+    }
+
+    /// Writes the CSV header for generations of a rule with `states` states.
+    pub fn write_csv_header(w: &mut impl Write, states: u8) -> io::Result<()> {
+        write!(
+            w,
+            "generation,alive,dying,births,deaths,\
+             min_x,min_y,min_z,max_x,max_y,max_z,center_x,center_y,center_z"
+        )?;
+        for health in 1..states.saturating_sub(1) {
+            write!(w, ",health_{health}")?;
+        }
+        writeln!(w)
+    }
+
+    /// Writes these stats as a CSV row. Fields without a value, like the
+    /// bounds of an empty generation, are left empty.
+    pub fn write_csv_row(&self, w: &mut impl Write, generation: u64) -> io::Result<()> {
+        write!(
+            w,
+            "{generation},{},{},{},{}",
+            self.alive,
+            self.dying_total(),
+            self.births,
+            self.deaths
+        )?;
+        match self.bounds {
+            Some((lo, hi)) => {
+                for c in lo.iter().chain(&hi) {
+                    write!(w, ",{c}")?;
+                }
+            }
+            None => write!(w, ",,,,,,")?,
+        }
+        match self.center_of_mass {
+            Some(c) => write!(w, ",{:.3},{:.3},{:.3}", c[0], c[1], c[2])?,
+            None => write!(w, ",,,")?,
+        }
+        for n in &self.dying {
+            write!(w, ",{n}")?;
+        }
+        writeln!(w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::{Boundary, Grid, Point},
+        rule::Rule,
+        sparse::SparseGrid,
+    };
+
+    #[test]
+    fn counts_a_generation() {
+        let rule: Rule = "2/1/4/M".parse().unwrap();
+        let mut grid = Grid::new([6, 6, 6]);
+        grid.set(&Point::new(1, 1, 1), CellStatus::Alive);
+        grid.set(&Point::new(3, 1, 1), CellStatus::Alive);
+        grid.set(&Point::new(5, 5, 5), CellStatus::Dying { health: 2 });
+        let first = Stats::new(&grid, None, rule.states);
+        assert_eq!(first.alive, 2);
+        assert_eq!(first.dying, vec![0, 1]);
+        assert_eq!((first.births, first.deaths), (0, 0));
+        // the dying cell is outside the box of the alive ones
+        assert_eq!(first.bounds, Some(([1, 1, 1], [3, 1, 1])));
+        assert_eq!(first.center_of_mass, Some([2., 1., 1.]));
+
+        // both cells die with no neighbors, and every cell next to exactly
+        // one of them is born
+        let next = grid.next(&rule, Boundary::DeadWall);
+        let stats = Stats::new(&next, Some(&grid), rule.states);
+        assert_eq!(stats.deaths, 2);
+        assert_eq!(stats.births, stats.alive);
+        assert_eq!(stats.dying, vec![1, 2]);
+        // the same on a sparse grid
+        let sparse = SparseGrid::from(&grid);
+        assert_eq!(
+            Stats::new(&sparse.next(&rule), Some(&sparse), rule.states),
+            stats
+        );
+
+        let empty = Stats::new(&Grid::new([2, 2, 2]), None, 2);
+        assert_eq!((empty.bounds, empty.center_of_mass), (None, None));
+    }
+
+    #[test]
+    fn writes_csv() {
+        let mut grid = Grid::new([4, 4, 4]);
+        grid.set(&Point::new(0, 1, 2), CellStatus::Alive);
+        let mut csv = Vec::new();
+        Stats::write_csv_header(&mut csv, 3).unwrap();
+        Stats::new(&grid, None, 3)
+            .write_csv_row(&mut csv, 7)
+            .unwrap();
+        Stats::new(&Grid::new([4, 4, 4]), Some(&grid), 3)
+            .write_csv_row(&mut csv, 8)
+            .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "generation,alive,dying,births,deaths,min_x,min_y,min_z,max_x,max_y,max_z,\
+                 center_x,center_y,center_z,health_1",
+                "7,1,0,0,0,0,1,2,0,1,2,0.000,1.000,2.000,0",
+                "8,0,0,0,1,,,,,,,,,,0",
+            ]
+        );
+    }
+}
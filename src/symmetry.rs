@@ -0,0 +1,294 @@
+//! Symmetries of the grid around its middle, used to make any seed
+//! symmetric.
+//!
+//! Each symmetry is a group of signed axis permutations: a cell `p` is moved
+//! to `q` with `q[i] = sign[i] * p[axis[i]]`, measured from the middle of the
+//! grid. Symmetrizing a grid gives every cell the state of one chosen cell of
+//! its orbit, the set of cells the group moves it to, so every cell of an
+//! orbit ends up the same.
+
+use crate::grid::{Grid, PackedCell};
+use itertools::iproduct;
+use rayon::prelude::*;
+use std::{fmt, str::FromStr};
+use strum::{EnumIter, IntoEnumIterator};
+
+/// How a seed is copied around the middle of the grid.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum Symmetry {
+    #[default]
+    None,
+    /// Mirrored across the middle of the x axis.
+    MirrorX,
+    /// Mirrored across the middles of the x and y axes.
+    MirrorXY,
+    /// Mirrored across the middles of all three axes.
+    MirrorXYZ,
+    /// The same after every quarter turn around the z axis.
+    Rotate4,
+    /// The same after every quarter turn around the z axis, and mirrored
+    /// across x, y and the diagonals between them.
+    Rotate8,
+    /// Every rotation and reflection of a cube: 48 copies.
+    Octahedral,
+}
+
+/// Moves cells measured from the middle: `q[i] = sign[i] * p[axis[i]]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transform {
+    pub axis: [usize; 3],
+    pub sign: [i64; 3],
+}
+
+impl Transform {
+    pub fn apply(&self, p: [i64; 3]) -> [i64; 3] {
+        [0, 1, 2].map(|i| self.sign[i] * p[self.axis[i]])
+    }
+
+    /// 1 if the transform is a rotation, -1 if it also mirrors.
+    fn determinant(&self) -> i64 {
+        let swaps = (0..3)
+            .flat_map(|i| (i + 1..3).map(move |j| (i, j)))
+            .filter(|(i, j)| self.axis[*i] > self.axis[*j])
+            .count();
+        let parity = if swaps % 2 == 0 { 1 } else { -1 };
+        parity * self.sign.iter().product::<i64>()
+    }
+}
+
+impl Symmetry {
+    /// A name for menus.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::MirrorX => "Mirror x",
+            Self::MirrorXY => "Mirror x, y",
+            Self::MirrorXYZ => "Mirror x, y, z",
+            Self::Rotate4 => "4-fold around z",
+            Self::Rotate8 => "8-fold around z",
+            Self::Octahedral => "Octahedral",
+        }
+    }
+
+    /// The transforms of the group, starting with the identity.
+    pub fn transforms(&self) -> Vec<Transform> {
+        let permutations = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        let signs = iproduct!([1, -1], [1, -1], [1, -1]).map(|(x, y, z)| [x, y, z]);
+        iproduct!(permutations, signs)
+            .map(|(axis, sign)| Transform { axis, sign })
+            .filter(|t| {
+                let unpermuted = t.axis == [0, 1, 2];
+                // fixes the z axis, so only turns or mirrors the x-y plane
+                let planar = t.axis[2] == 2 && t.sign[2] == 1;
+                match self {
+                    Self::None => unpermuted && t.sign == [1, 1, 1],
+                    Self::MirrorX => unpermuted && t.sign[1..] == [1, 1],
+                    Self::MirrorXY => unpermuted && t.sign[2] == 1,
+                    Self::MirrorXYZ => unpermuted,
+                    Self::Rotate4 => planar && t.determinant() == 1,
+                    Self::Rotate8 => planar,
+                    Self::Octahedral => true,
+                }
+            })
+            .collect()
+    }
+
+    /// For a grid of `dims` cells, the cell whose state
+    /// [`Grid::symmetrized`] gives to each cell: the first of its orbit, in
+    /// grid order.
+    pub fn source(&self, dims: [usize; 3]) -> impl Fn([usize; 3]) -> [usize; 3] + Sync {
+        let transforms = self.transforms();
+        move |p| {
+            let centered = to_centered(dims, p);
+            transforms
+                .iter()
+                .filter_map(|t| from_centered(dims, t.apply(centered)))
+                .min()
+                .unwrap_or(p)
+        }
+    }
+
+    /// Whether a grid of `dims` cells can hold the whole symmetry. Turns
+    /// around z need a square cross-section, and octahedral symmetry a cube;
+    /// otherwise cells moved off the grid are ignored.
+    pub fn fits(&self, dims: [usize; 3]) -> bool {
+        match self {
+            Self::Rotate4 | Self::Rotate8 => dims[0] == dims[1],
+            Self::Octahedral => dims[0] == dims[1] && dims[1] == dims[2],
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::MirrorX => "mirror-x",
+            Self::MirrorXY => "mirror-xy",
+            Self::MirrorXYZ => "mirror-xyz",
+            Self::Rotate4 => "rotate-4",
+            Self::Rotate8 => "rotate-8",
+            Self::Octahedral => "octahedral",
+        })
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::iter()
+            .find(|sym| sym.to_string() == s.trim())
+            .ok_or(format!("unknown symmetry {s}"))
+    }
+}
+
+impl Grid {
+    /// A copy of the grid with `symmetry`: each cell takes the state of the
+    /// first cell of its orbit, in grid order.
+    pub fn symmetrized(&self, symmetry: Symmetry) -> Grid {
+        if symmetry == Symmetry::None {
+            return self.clone();
+        }
+        let dims = self.dims();
+        let source = symmetry.source(dims);
+        let [_, h, d] = dims;
+        let cells = self.packed();
+        let symmetric = (0..cells.len())
+            .into_par_iter()
+            .map(|i| {
+                let [x, y, z] = source([i / (h * d), i / d % h, i % d]);
+                cells[(x * h + y) * d + z]
+            })
+            .collect::<Vec<PackedCell>>();
+        Grid::from_packed(dims, symmetric)
+    }
+}
+
+/// Twice the offset of `p` from the middle of a grid of `dims` cells, so it's
+/// whole on even axes too.
+fn to_centered(dims: [usize; 3], p: [usize; 3]) -> [i64; 3] {
+    [0, 1, 2].map(|i| 2 * p[i] as i64 - (dims[i] as i64 - 1))
+}
+
+/// The cell at twice the offset `c` from the middle, if there's one there.
+fn from_centered(dims: [usize; 3], c: [i64; 3]) -> Option<[usize; 3]> {
+    let mut p = [0; 3];
+    for i in 0..3 {
+        let doubled = c[i] + dims[i] as i64 - 1;
+        if doubled < 0 || doubled % 2 != 0 || doubled / 2 >= dims[i] as i64 {
+            return None;
+        }
+        p[i] = (doubled / 2) as usize;
+    }
+    Some(p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cell::CellStatus,
+        grid::{Boundary, NoiseSettings, Point},
+        rule::Rule,
+    };
+
+    fn is_symmetric(g: &Grid, symmetry: Symmetry) -> bool {
+        let dims = g.dims();
+        let transforms = symmetry.transforms();
+        g.iter().all(|(p, c)| {
+            let p = to_centered(dims, p.into());
+            transforms.iter().all(|t| {
+                let [x, y, z] = from_centered(dims, t.apply(p)).unwrap();
+                g.get(&Point::new(x, y, z)) == Some(c)
+            })
+        })
+    }
+
+    #[test]
+    fn group_sizes() {
+        let sizes = Symmetry::iter()
+            .map(|s| s.transforms().len())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [1, 2, 4, 8, 4, 8, 48]);
+        for s in Symmetry::iter() {
+            let transforms = s.transforms();
+            assert_eq!(transforms[0].apply([1, 2, 3]), [1, 2, 3]);
+            // closed under composition
+            for (a, b) in iproduct!(&transforms, &transforms) {
+                let p = b.apply(a.apply([1, 2, 3]));
+                assert!(transforms.iter().any(|t| t.apply([1, 2, 3]) == p), "{s}");
+            }
+            assert_eq!(s.to_string().parse(), Ok(s));
+        }
+    }
+
+    #[test]
+    fn symmetrized_seeds() {
+        let noise = NoiseSettings {
+            threshold: 0.,
+            ..Default::default()
+        };
+        let dims = [24, 24, 24];
+        let g = Grid::new_noise(dims, &noise);
+        for s in Symmetry::iter() {
+            let sym = g.symmetrized(s);
+            assert!(is_symmetric(&sym, s), "{s}");
+            assert!(sym.iter().any(|(_, c)| c == CellStatus::Alive), "{s}");
+        }
+        assert!(!is_symmetric(&g, Symmetry::MirrorX));
+        // odd sizes have a middle layer that mirrors onto itself
+        let odd = Grid::new_noise([15, 9, 7], &noise).symmetrized(Symmetry::MirrorXYZ);
+        assert!(is_symmetric(&odd, Symmetry::MirrorXYZ));
+    }
+
+    #[test]
+    fn symmetric_slices() {
+        let noise = NoiseSettings {
+            threshold: 0.,
+            ..Default::default()
+        };
+        let dims = [20, 14, 9];
+        let g = Grid::new_noise(dims, &noise);
+        for s in [Symmetry::MirrorX, Symmetry::MirrorXYZ] {
+            let sym = g.symmetrized(s);
+            for z in [0, 4, 8] {
+                let layer = iproduct!(0..dims[1], 0..dims[0])
+                    .map(|(y, x)| sym.get(&Point::new(x, y, z)) == Some(CellStatus::Alive))
+                    .collect::<Vec<_>>();
+                assert_eq!(noise.slice(dims, z, s), layer, "{s} {z}");
+            }
+        }
+    }
+
+    #[test]
+    fn symmetric_rules_keep_symmetry() {
+        // Moore and von Neumann neighborhoods look the same from every
+        // direction, so any rule using them keeps every symmetry
+        let rules = ["4/4/5/M", "2-6/3/3/N2", "9-26/5-7,12-13,15/5/M"];
+        let dims = [20, 20, 20];
+        for (rule, s) in iproduct!(rules, Symmetry::iter()) {
+            let rule: Rule = rule.parse().unwrap();
+            let noise = NoiseSettings {
+                size: 6,
+                ..Default::default()
+            };
+            let mut g = Grid::new_noise(dims, &noise).symmetrized(s);
+            for generation in 0..8 {
+                for boundary in [Boundary::DeadWall, Boundary::Torus] {
+                    let next = g.next(&rule, boundary);
+                    assert!(is_symmetric(&next, s), "{rule:?} {s} {generation}");
+                }
+                g = g.next(&rule, Boundary::DeadWall);
+            }
+        }
+    }
+}
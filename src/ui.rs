@@ -0,0 +1,1173 @@
+use crate::{
+    camera::AutoRotate,
+    grid::{Boundary, CoreShape, NoiseSettings},
+    plugin::{
+        ClipMode, ClipPlane, Clipping, CullHidden, Cycles, Generation, GridReset, GridSettings,
+        MainGrid, OnCycle, Playback, PlaybackControl, Population, SnapshotRequest, SnapshotStatus,
+        Timeline,
+    },
+    preset::Preset,
+    rendering::InstanceMaterialData,
+    rule::{Neighbors, Notation, ParseRuleError, Rule},
+    seed::{Blend, Generator, Layer, NoiseKind, Seed},
+    stats::Stats,
+    symmetry::Symmetry,
+};
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, ecolor::Hsva, text::LayoutJob, Color32, RichText, Stroke, TextFormat},
+    EguiContexts,
+};
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    time::Duration,
+};
+use strum::Display;
+use strum::IntoEnumIterator;
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_window(
+    mut contexts: EguiContexts,
+    mut rule: ResMut<Rule>,
+    mut rule_str: Local<String>,
+    mut ev: EventWriter<GridReset>,
+    mut n: ResMut<NoiseSettings>,
+    mut boundary: ResMut<Boundary>,
+    mut mask: Local<MaskEditor>,
+    mut settings: ResMut<GridSettings>,
+    mut snapshot_path: Local<String>,
+    mut snapshots: EventWriter<SnapshotRequest>,
+    snapshot_status: Res<SnapshotStatus>,
+    mut notation: Local<Notation>,
+    mut presets: Local<PresetLibrary>,
+    mut seed: ResMut<Seed>,
+    mut preview: Local<SeedPreview>,
+) {
+    // follow the live rule whenever it's replaced, including at startup
+    if rule.is_changed() {
+        *rule_str = write_rule(&rule, *notation);
+    }
+    if snapshot_path.is_empty() {
+        *snapshot_path = "grid.ca3d".into();
+    }
+    // parsed once a frame, and again only after the text is edited
+    let mut parsed = rule_str.parse::<Rule>();
+    egui::Window::new("Settings")
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if let Some(p) = preset_menu(ui, &mut presets) {
+                *rule = p.rule;
+                *n = p.noise;
+                settings.dims = p.dims;
+                settings.layered = false;
+                ev.send(GridReset);
+            }
+            ui.horizontal(|ui| {
+                ui.label("Rule");
+                let before = *notation;
+                egui::ComboBox::from_id_source("notation")
+                    .selected_text(notation.to_string())
+                    .show_ui(ui, |ui| {
+                        for n in Notation::iter() {
+                            ui.selectable_value(&mut *notation, n, n.to_string());
+                        }
+                    });
+                if *notation != before {
+                    // rewrite what's typed if it parses, or else the live rule
+                    *rule_str = write_rule(parsed.as_ref().unwrap_or(&rule), *notation);
+                    parsed = rule_str.parse();
+                }
+            });
+            let shown = rule_str.clone();
+            let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                // the text only differs from what was parsed on a frame it's edited
+                let mut job = if text == shown {
+                    highlight_rule(ui, text, &parsed)
+                } else {
+                    highlight_rule(ui, text, &text.parse())
+                };
+                job.wrap.max_width = wrap_width;
+                ui.fonts(|f| f.layout_job(job))
+            };
+            let edit = ui.add(egui::TextEdit::singleline(&mut *rule_str).layouter(&mut layouter));
+            if edit.changed() {
+                parsed = rule_str.parse();
+            }
+            if let Err(e) = &parsed {
+                for d in &e.diagnostics {
+                    let message = match &rule_str[d.span.clone()] {
+                        "" => d.message.clone(),
+                        part => format!("{part}: {}", d.message),
+                    };
+                    ui.label(RichText::new(message).color(Color32::RED));
+                }
+            }
+            ui.checkbox(&mut settings.unbounded, "Unbounded")
+                .on_hover_text("Let the grid grow without edges; applies on restart");
+            ui.horizontal(|ui| {
+                for (d, name) in settings.dims.iter_mut().zip(["W", "H", "D"]) {
+                    ui.label(name);
+                    ui.add(egui::DragValue::new(d).clamp_range(1..=400));
+                }
+                ui.label("Size");
+            })
+            .response
+            .on_hover_text("Width, height and depth in cells; applies on restart");
+            ui.add_enabled_ui(!settings.unbounded, |ui| {
+                egui::ComboBox::from_label("Boundary")
+                    .selected_text(boundary.to_string())
+                    .show_ui(ui, |ui| {
+                        // only a new choice counts as a change, which
+                        // recomputes the next generation
+                        let mut selected = *boundary;
+                        for b in Boundary::iter() {
+                            ui.selectable_value(&mut selected, b, b.to_string());
+                        }
+                        boundary.set_if_neq(selected);
+                    });
+            });
+            ui.horizontal(|ui| {
+                ui.label("Seed from");
+                ui.radio_value(&mut settings.layered, false, "Noise core");
+                ui.radio_value(&mut settings.layered, true, "Layers");
+            });
+            if settings.layered {
+                seed_editor(ui, &mut seed);
+            } else {
+                ui.add(
+                    egui::Slider::new(&mut n.seed, 0..=u32::MAX)
+                        .text("Seed")
+                        .drag_value_speed(100.),
+                );
+                ui.add(egui::Slider::new(&mut n.threshold, -1. ..=1.).text("Threshold"));
+                noise_shaping(ui, &mut n);
+            }
+            egui::ComboBox::from_label("Symmetry")
+                .selected_text(settings.symmetry.name())
+                .show_ui(ui, |ui| {
+                    for s in Symmetry::iter() {
+                        ui.selectable_value(&mut settings.symmetry, s, s.name());
+                    }
+                })
+                .response
+                .on_hover_text("Copies the seed around the middle of the grid");
+            if !settings.symmetry.fits(settings.dims) {
+                ui.label(
+                    "Cells turned off the grid are left out; turns need equal width and height",
+                );
+            }
+            seed_preview(ui, &mut preview, &settings, &n, &seed);
+            if let Ok(r) = &parsed {
+                if r.notated(*notation).is_none() {
+                    ui.label(format!("{} notation can't express this rule", *notation));
+                }
+            }
+            if mask_editor(ui, &mut mask, &mut rule_str, &parsed, *notation) {
+                parsed = rule_str.parse();
+            }
+            let restart = ui
+                .add_enabled(parsed.is_ok(), egui::Button::new("Restart"))
+                .on_disabled_hover_text("Fix the rule first");
+            if let (true, Ok(r)) = (restart.clicked(), &parsed) {
+                *rule = r.clone();
+                ev.send(GridReset);
+            }
+            let current = |name: &str, description: &str| Preset {
+                name: name.into(),
+                description: description.into(),
+                rule: parsed.as_ref().unwrap_or(&rule).clone(),
+                noise: n.clone(),
+                dims: settings.dims,
+            };
+            preset_editor(ui, &mut presets, current);
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Snapshot");
+                ui.text_edit_singleline(&mut *snapshot_path);
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    snapshots.send(SnapshotRequest::Save(snapshot_path.as_str().into()));
+                }
+                if ui.button("Load").clicked() {
+                    snapshots.send(SnapshotRequest::Load(snapshot_path.as_str().into()));
+                }
+                match &snapshot_status.0 {
+                    Ok(msg) => ui.label(msg),
+                    Err(e) => ui.label(RichText::new(e).color(Color32::RED)),
+                };
+            });
+        });
+}
+
+/// Lets the user change how the noise is sampled and which part of the grid
+/// it seeds.
+fn noise_shaping(ui: &mut egui::Ui, n: &mut NoiseSettings) {
+    ui.add(
+        egui::Slider::new(&mut n.frequency, 0.01..=4.)
+            .logarithmic(true)
+            .text("Frequency"),
+    )
+    .on_hover_text("Lower values give larger, smoother blobs");
+    for (name, values, speed, hover) in [
+        ("Offset", &mut n.offset, 0.1, "Moves the noise through the grid"),
+        (
+            "Scale",
+            &mut n.scale,
+            0.01,
+            "Multiplies the frequency along x, y and z, stretching the noise along axes with smaller values",
+        ),
+    ] {
+        ui.horizontal(|ui| {
+            for v in values.iter_mut() {
+                ui.add(egui::DragValue::new(v).speed(speed).max_decimals(2));
+            }
+            ui.label(name);
+        })
+        .response
+        .on_hover_text(hover);
+    }
+    egui::ComboBox::from_label("Core Shape")
+        .selected_text(n.shape.name())
+        .show_ui(ui, |ui| {
+            for shape in CoreShape::all(n.size) {
+                let selected = shape.name() == n.shape.name();
+                if ui.selectable_label(selected, shape.name()).clicked() && !selected {
+                    n.shape = shape;
+                }
+            }
+        });
+    match &mut n.shape {
+        CoreShape::Ellipsoid(radii) => {
+            ui.horizontal(|ui| {
+                for r in radii.iter_mut() {
+                    ui.add(egui::DragValue::new(r).clamp_range(1..=200));
+                }
+                ui.label("Radii");
+            });
+        }
+        CoreShape::Full => {}
+        _ => {
+            ui.add(egui::Slider::new(&mut n.size, 1..=50).text("Core Size"));
+        }
+    }
+}
+
+/// A picture of one layer of the grid that restarting would seed, redrawn
+/// only when the settings change.
+#[derive(Default)]
+pub(crate) struct SeedPreview {
+    texture: Option<egui::TextureHandle>,
+    /// What `texture` shows, so it's only redrawn when that changes.
+    shown: Option<(NoiseSettings, Seed, bool, Symmetry, [usize; 3], usize)>,
+    /// The layer shown, counted from the middle so it stays there when the
+    /// depth changes.
+    z: i64,
+}
+
+fn seed_preview(
+    ui: &mut egui::Ui,
+    preview: &mut SeedPreview,
+    settings: &GridSettings,
+    n: &NoiseSettings,
+    seed: &Seed,
+) {
+    egui::CollapsingHeader::new("Seed Preview").show(ui, |ui| {
+        let dims = settings.dims;
+        let half = (dims[2] / 2) as i64;
+        ui.add(egui::Slider::new(&mut preview.z, -half..=dims[2] as i64 - 1 - half).text("Layer"))
+            .on_hover_text("Depth of the slice, counted from the middle");
+        let z = (half + preview.z).clamp(0, dims[2] as i64 - 1) as usize;
+        let key = (
+            n.clone(),
+            seed.clone(),
+            settings.layered,
+            settings.symmetry,
+            dims,
+            z,
+        );
+        if preview.shown.as_ref() != Some(&key) {
+            let alive = if settings.layered {
+                seed.slice(dims, z, settings.symmetry)
+            } else {
+                n.slice(dims, z, settings.symmetry)
+            };
+            let pixels = alive
+                .iter()
+                .map(|a| if *a { Color32::WHITE } else { Color32::BLACK })
+                .collect();
+            let image = egui::ColorImage {
+                size: [dims[0], dims[1]],
+                pixels,
+            };
+            match &mut preview.texture {
+                Some(t) => t.set(image, egui::TextureOptions::NEAREST),
+                None => {
+                    preview.texture = Some(ui.ctx().load_texture(
+                        "seed preview",
+                        image,
+                        egui::TextureOptions::NEAREST,
+                    ))
+                }
+            }
+            preview.shown = Some(key);
+        }
+        if let Some(t) = &preview.texture {
+            // fit the longer side to a fixed width, keeping cells square
+            let size = egui::vec2(dims[0] as f32, dims[1] as f32);
+            let size = size * (200. / size.max_elem());
+            ui.image(egui::load::SizedTexture::new(t.id(), size));
+        }
+        if settings.unbounded {
+            ui.label("Unbounded grids seed a box of this size");
+        }
+    });
+}
+
+/// A window with the playback controls, the current generation and the
+/// timeline of past generations.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_playback(
+    mut contexts: EguiContexts,
+    playback: Res<Playback>,
+    generation: Res<Generation>,
+    mut timeline: ResMut<Timeline>,
+    mut cycles: ResMut<Cycles>,
+    mut controls: EventWriter<PlaybackControl>,
+    mut run_for: Local<Option<u64>>,
+    mut auto_rotate: ResMut<AutoRotate>,
+) {
+    let run_for = run_for.get_or_insert(10);
+    egui::Window::new("Playback")
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("Generation {}", generation.0));
+            ui.horizontal(|ui| {
+                let label = if playback.paused { "Play" } else { "Pause" };
+                if ui.button(label).on_hover_text("Space").clicked() {
+                    controls.send(PlaybackControl::TogglePause);
+                }
+                let can_rewind = timeline
+                    .history
+                    .as_ref()
+                    .is_some_and(|h| h.first() < generation.0);
+                if ui
+                    .add_enabled(can_rewind, egui::Button::new("Back"))
+                    .on_hover_text("Go back one generation (Left arrow)")
+                    .clicked()
+                {
+                    controls.send(PlaybackControl::StepBack);
+                }
+                if ui
+                    .button("Step")
+                    .on_hover_text("Advance one generation (Right arrow)")
+                    .clicked()
+                {
+                    controls.send(PlaybackControl::Step);
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Run").clicked() {
+                    controls.send(PlaybackControl::Run(*run_for));
+                }
+                ui.add(egui::DragValue::new(run_for).clamp_range(1..=100_000));
+                ui.label("generations");
+                if let Some(remaining) = playback.remaining {
+                    ui.label(format!("({remaining} left)"));
+                }
+            });
+            let mut max_speed = playback.max_speed;
+            if ui
+                .checkbox(&mut max_speed, "Max speed")
+                .on_hover_text("Step as soon as each generation is computed")
+                .changed()
+            {
+                controls.send(PlaybackControl::SetMaxSpeed(max_speed));
+            }
+            let mut ms = playback.interval.as_millis() as u64;
+            let slider = ui.add_enabled(
+                !playback.max_speed,
+                egui::Slider::new(&mut ms, 10..=2000)
+                    .logarithmic(true)
+                    .suffix(" ms")
+                    .text("Tick"),
+            );
+            if slider.changed() {
+                controls.send(PlaybackControl::SetInterval(Duration::from_millis(ms)));
+            }
+            match cycles.cycle() {
+                Some(cycle) => ui.label(RichText::new(cycle.to_string()).strong()),
+                None => ui.label("No repeats found"),
+            };
+            egui::ComboBox::from_label("When it repeats")
+                .selected_text(cycles.on_cycle.to_string())
+                .show_ui(ui, |ui| {
+                    for a in OnCycle::iter() {
+                        ui.selectable_value(&mut cycles.on_cycle, a, a.to_string());
+                    }
+                });
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut auto_rotate.enabled, "Auto-rotate");
+                ui.add_enabled(
+                    auto_rotate.enabled,
+                    egui::Slider::new(&mut auto_rotate.speed, -3. ..=3.).suffix(" rad/s"),
+                );
+            })
+            .response
+            .on_hover_text(
+                "Drag to orbit, scroll to zoom, middle-drag to pan and F to look at the middle",
+            );
+            ui.separator();
+            let Some(history) = &timeline.history else {
+                ui.label("Unbounded grids have no history");
+                return;
+            };
+            let mut shown = generation.0;
+            let scrubber =
+                egui::Slider::new(&mut shown, history.first()..=history.latest()).text("History");
+            if ui.add(scrubber).changed() {
+                controls.send(PlaybackControl::Seek(shown));
+            }
+            if generation.0 < history.latest() {
+                ui.label("Playing or stepping continues from here, discarding later generations");
+            }
+            let memory = history.memory() as f64 / (1 << 20) as f64;
+            ui.horizontal(|ui| {
+                ui.label("Keep");
+                let capacity =
+                    ui.add(egui::DragValue::new(&mut timeline.capacity).clamp_range(1..=100_000));
+                ui.label(format!("generations ({memory:.1} MiB)"));
+                if capacity.changed() {
+                    let capacity = timeline.capacity;
+                    if let Some(h) = &mut timeline.history {
+                        h.set_capacity(capacity);
+                    }
+                }
+            });
+        });
+}
+
+/// A window for hiding part of the grid, to see inside it.
+pub(crate) fn draw_clipping(
+    mut contexts: EguiContexts,
+    mut clipping: ResMut<Clipping>,
+    mut cull: ResMut<CullHidden>,
+    settings: Res<GridSettings>,
+    drawn: Query<&InstanceMaterialData, With<MainGrid>>,
+) {
+    egui::Window::new("Cross Section")
+        .default_open(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for mode in ClipMode::iter() {
+                    ui.radio_value(&mut clipping.mode, mode, mode.to_string());
+                }
+            });
+            // unbounded grids grow past the seeded box
+            let scale = if settings.unbounded { 4. } else { 1. };
+            let extent = settings.dims.map(|d| d as f32 / 2. * scale);
+            match clipping.mode {
+                ClipMode::Off => {
+                    ui.label("Every cell is drawn");
+                }
+                ClipMode::Planes => {
+                    egui::Grid::new("clipping planes").show(ui, |ui| {
+                        for (i, axis) in ["x", "y", "z"].into_iter().enumerate() {
+                            let range = -extent[i]..=extent[i];
+                            ui.label(axis);
+                            let min = ui.add(
+                                egui::Slider::new(&mut clipping.min[i], range.clone())
+                                    .step_by(1.)
+                                    .text("from"),
+                            );
+                            let max = ui.add(
+                                egui::Slider::new(&mut clipping.max[i], range)
+                                    .step_by(1.)
+                                    .text("to"),
+                            );
+                            // keep at least one layer
+                            if min.changed() {
+                                clipping.max[i] = clipping.max[i].max(clipping.min[i]);
+                            }
+                            if max.changed() {
+                                clipping.min[i] = clipping.min[i].min(clipping.max[i]);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    let mut angled = clipping.cut.is_some();
+                    ui.checkbox(&mut angled, "Cut at an angle")
+                        .on_hover_text("Hides the cells in front of a plane facing any direction");
+                    match (angled, &mut clipping.cut) {
+                        (true, Some(cut)) => {
+                            let reach = extent.iter().map(|e| e * e).sum::<f32>().sqrt();
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut cut.azimuth,
+                                    -std::f32::consts::PI..=std::f32::consts::PI,
+                                )
+                                .text("Facing around"),
+                            );
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut cut.elevation,
+                                    -std::f32::consts::FRAC_PI_2..=std::f32::consts::FRAC_PI_2,
+                                )
+                                .text("Facing up"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut cut.offset, -reach..=reach).text("Offset"),
+                            );
+                        }
+                        (true, None) => clipping.cut = Some(ClipPlane::default()),
+                        (false, _) => clipping.cut = None,
+                    }
+                    if ui.button("Show All").clicked() {
+                        *clipping = Clipping {
+                            mode: ClipMode::Planes,
+                            ..default()
+                        };
+                    }
+                }
+                ClipMode::Slice => {
+                    ui.horizontal(|ui| {
+                        ui.label("Across");
+                        for (i, axis) in ["x", "y", "z"].into_iter().enumerate() {
+                            ui.radio_value(&mut clipping.slice_axis, i, axis);
+                        }
+                    });
+                    let e = extent[clipping.slice_axis];
+                    ui.add(
+                        egui::Slider::new(&mut clipping.slice_at, -e..=e)
+                            .step_by(1.)
+                            .text("Layer"),
+                    )
+                    .on_hover_text("Cells from the middle of the grid");
+                }
+            }
+            ui.separator();
+            ui.checkbox(&mut cull.0, "Skip hidden cells").on_hover_text(
+                "Leave out cells surrounded on all six sides. Much faster for solid patterns, \
+                 but the cells inside no longer show through the gaps between cubes",
+            );
+            if let Ok(drawn) = drawn.get_single() {
+                ui.label(format!("Drawing {} cells", drawn.len()));
+            }
+        });
+}
+
+/// What the statistics window plots.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Display)]
+pub(crate) enum Plot {
+    #[default]
+    Population,
+    #[strum(serialize = "Dying by health")]
+    Health,
+    #[strum(serialize = "Bounding box")]
+    Bounds,
+    #[strum(serialize = "Center of mass")]
+    Center,
+}
+
+/// State of the statistics window.
+pub(crate) struct StatsPanel {
+    plot: Plot,
+    path: String,
+    status: Result<String, String>,
+}
+
+impl Default for StatsPanel {
+    fn default() -> Self {
+        Self {
+            plot: Plot::default(),
+            path: "stats.csv".into(),
+            status: Ok(String::new()),
+        }
+    }
+}
+
+/// A window with the stats of the shown generation, plots of the whole run
+/// and CSV export.
+pub(crate) fn draw_stats(
+    mut contexts: EguiContexts,
+    population: Res<Population>,
+    generation: Res<Generation>,
+    rule: Res<Rule>,
+    mut panel: Local<StatsPanel>,
+) {
+    egui::Window::new("Statistics")
+        .default_open(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let series = &population.0;
+            let Some((_, current)) = series.iter().find(|(g, _)| *g == generation.0) else {
+                ui.label("No statistics yet");
+                return;
+            };
+            egui::Grid::new("current stats").show(ui, |ui| {
+                let vec = |v: [String; 3]| v.join(", ");
+                let rows = [
+                    ("Alive", current.alive.to_string()),
+                    ("Dying", current.dying_total().to_string()),
+                    ("Births", current.births.to_string()),
+                    ("Deaths", current.deaths.to_string()),
+                    (
+                        "Bounds",
+                        current.bounds.map_or("-".into(), |(lo, hi)| {
+                            format!(
+                                "({}) to ({})",
+                                vec(lo.map(|c| c.to_string())),
+                                vec(hi.map(|c| c.to_string()))
+                            )
+                        }),
+                    ),
+                    (
+                        "Center of mass",
+                        current.center_of_mass.map_or("-".into(), |c| {
+                            format!("({})", vec(c.map(|c| format!("{c:.1}"))))
+                        }),
+                    ),
+                ];
+                for (name, value) in rows {
+                    ui.label(name);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+            egui::ComboBox::from_label("Plot")
+                .selected_text(panel.plot.to_string())
+                .show_ui(ui, |ui| {
+                    for p in [Plot::Population, Plot::Health, Plot::Bounds, Plot::Center] {
+                        ui.selectable_value(&mut panel.plot, p, p.to_string());
+                    }
+                });
+            let line = |name: &str, color: Color32, value: &dyn Fn(&Stats) -> Option<f64>| {
+                (
+                    name.to_string(),
+                    color,
+                    series.iter().map(|(g, s)| (*g, value(s))).collect(),
+                )
+            };
+            let axes = [Color32::RED, Color32::GREEN, Color32::LIGHT_BLUE];
+            let lines: Vec<Line> = match panel.plot {
+                Plot::Population => vec![
+                    line("alive", Color32::RED, &|s| Some(s.alive as f64)),
+                    line("dying", Color32::GRAY, &|s| Some(s.dying_total() as f64)),
+                    line("births", Color32::GREEN, &|s| Some(s.births as f64)),
+                    line("deaths", Color32::LIGHT_BLUE, &|s| Some(s.deaths as f64)),
+                ],
+                Plot::Health => (1..rule.states.saturating_sub(1))
+                    .map(|health| {
+                        let hue = health as f32 / rule.states as f32;
+                        let i = usize::from(health) - 1;
+                        line(
+                            &format!("health {health}"),
+                            Hsva::new(hue, 0.8, 0.9, 1.).into(),
+                            &|s| Some(s.dying.get(i).copied().unwrap_or(0) as f64),
+                        )
+                    })
+                    .collect(),
+                Plot::Bounds => ["width", "height", "depth"]
+                    .into_iter()
+                    .zip(axes)
+                    .enumerate()
+                    .map(|(d, (name, color))| {
+                        line(name, color, &|s| {
+                            s.bounds.map(|(lo, hi)| (hi[d] - lo[d] + 1) as f64)
+                        })
+                    })
+                    .collect(),
+                Plot::Center => ["x", "y", "z"]
+                    .into_iter()
+                    .zip(axes)
+                    .enumerate()
+                    .map(|(d, (name, color))| {
+                        line(name, color, &|s| s.center_of_mass.map(|c| c[d]))
+                    })
+                    .collect(),
+            };
+            line_plot(ui, &lines, generation.0);
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("CSV");
+                ui.text_edit_singleline(&mut panel.path);
+                if ui.button("Export").clicked() {
+                    panel.status = export_csv(&panel.path, series, rule.states)
+                        .map(|_| format!("exported {} generations", series.len()))
+                        .map_err(|e| e.to_string());
+                }
+            });
+            match &panel.status {
+                Ok(msg) => ui.label(msg),
+                Err(e) => ui.label(RichText::new(e).color(Color32::RED)),
+            };
+        });
+}
+
+fn export_csv(path: &str, series: &[(u64, Stats)], states: u8) -> std::io::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    Stats::write_csv_header(&mut f, states)?;
+    for (generation, stats) in series {
+        stats.write_csv_row(&mut f, *generation)?;
+    }
+    f.flush()
+}
+
+/// A named, colored series of values by generation, with gaps where there's
+/// no value.
+type Line = (String, Color32, Vec<(u64, Option<f64>)>);
+
+/// Draws `lines` scaled to fit, with a marker at generation `marker` and a
+/// legend below.
+fn line_plot(ui: &mut egui::Ui, lines: &[Line], marker: u64) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(320., 140.), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_stroke(rect, 0., visuals.widgets.noninteractive.bg_stroke);
+    let points = || lines.iter().flat_map(|(_, _, l)| l.iter());
+    let (x0, x1) = points().fold((u64::MAX, 0), |(lo, hi), (g, _)| (lo.min(*g), hi.max(*g)));
+    let (y0, y1) = points()
+        .filter_map(|(_, v)| *v)
+        .fold((0f64, 1f64), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    if x0 > x1 {
+        return;
+    }
+    let to_screen = |g: u64, v: f64| {
+        let x = (g - x0) as f32 / (x1 - x0).max(1) as f32;
+        let y = ((v - y0) / (y1 - y0)) as f32;
+        egui::pos2(
+            rect.left() + x * rect.width(),
+            rect.bottom() - y * rect.height(),
+        )
+    };
+    // about one point per pixel is as much as can be seen
+    let every = (lines.first().map_or(0, |(_, _, l)| l.len()) / rect.width() as usize).max(1);
+    for (_, color, line) in lines {
+        let mut segment = vec![];
+        for (g, v) in line.iter().step_by(every).chain(line.last()) {
+            match v {
+                Some(v) => segment.push(to_screen(*g, *v)),
+                None => {
+                    painter.add(egui::Shape::line(
+                        std::mem::take(&mut segment),
+                        (1.5, *color),
+                    ));
+                }
+            }
+        }
+        painter.add(egui::Shape::line(segment, (1.5, *color)));
+    }
+    if (x0..=x1).contains(&marker) {
+        let x = to_screen(marker, 0.).x;
+        painter.vline(x, rect.y_range(), (1., visuals.weak_text_color()));
+    }
+    let font = egui::TextStyle::Small.resolve(ui.style());
+    let text = visuals.text_color();
+    painter.text(
+        rect.left_top(),
+        egui::Align2::LEFT_TOP,
+        format!("{y1:.0}"),
+        font.clone(),
+        text,
+    );
+    painter.text(
+        rect.left_bottom(),
+        egui::Align2::LEFT_BOTTOM,
+        format!("{y0:.0}"),
+        font.clone(),
+        text,
+    );
+    painter.text(
+        rect.right_bottom(),
+        egui::Align2::RIGHT_BOTTOM,
+        format!("generations {x0}-{x1}"),
+        font,
+        text,
+    );
+    ui.horizontal_wrapped(|ui| {
+        for (name, color, _) in lines {
+            ui.label(RichText::new(name).color(*color));
+        }
+    });
+}
+
+/// `rule` in `notation`, or in the standard notation if `notation` can't
+/// express it.
+fn write_rule(rule: &Rule, notation: Notation) -> String {
+    rule.notated(notation)
+        .map_or_else(|| rule.to_string(), |n| n.to_string())
+}
+
+/// Lays out a rule string with the parts that don't parse underlined in red,
+/// given what parsing it gave.
+fn highlight_rule(ui: &egui::Ui, text: &str, parsed: &Result<Rule, ParseRuleError>) -> LayoutJob {
+    let spans = parsed
+        .as_ref()
+        .err()
+        .map(|e| {
+            e.diagnostics
+                .iter()
+                .map(|d| d.span.clone())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let mut bounds = spans
+        .iter()
+        .flat_map(|s| [s.start, s.end])
+        .chain([0, text.len()])
+        .collect::<Vec<_>>();
+    bounds.sort();
+    bounds.dedup();
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let mut job = LayoutJob::default();
+    for part in bounds.windows(2).map(|b| b[0]..b[1]) {
+        let bad = spans
+            .iter()
+            .any(|s| s.start <= part.start && part.end <= s.end);
+        let format = if bad {
+            TextFormat {
+                font_id: font_id.clone(),
+                color: Color32::RED,
+                underline: Stroke::new(1., Color32::RED),
+                ..default()
+            }
+        } else {
+            TextFormat {
+                font_id: font_id.clone(),
+                color: ui.visuals().text_color(),
+                ..default()
+            }
+        };
+        job.append(&text[part], 0., format);
+    }
+    job
+}
+
+/// The built-in presets, the user's own, and the state of the preset editor.
+pub(crate) struct PresetLibrary {
+    builtin: Vec<Preset>,
+    user: Vec<Preset>,
+    /// Name of the preset last picked from the menu.
+    selected: String,
+    name: String,
+    description: String,
+    path: String,
+    status: Result<String, String>,
+}
+
+impl Default for PresetLibrary {
+    fn default() -> Self {
+        Self {
+            builtin: Preset::builtin(),
+            user: vec![],
+            selected: String::new(),
+            name: String::new(),
+            description: String::new(),
+            path: "my_presets.txt".into(),
+            status: Ok(String::new()),
+        }
+    }
+}
+
+/// A dropdown of every preset, returning the one picked this frame.
+fn preset_menu(ui: &mut egui::Ui, presets: &mut PresetLibrary) -> Option<Preset> {
+    let mut picked = None;
+    egui::ComboBox::from_label("Preset")
+        .selected_text(presets.selected.as_str())
+        .show_ui(ui, |ui| {
+            let user = presets.user.iter().map(|p| (p, "yours"));
+            for (p, origin) in presets.builtin.iter().map(|p| (p, "built-in")).chain(user) {
+                let hover = match p.description.as_str() {
+                    "" => format!("{} ({origin})", p.rule),
+                    d => format!("{d}\n{} ({origin})", p.rule),
+                };
+                if ui
+                    .selectable_label(presets.selected == p.name, &p.name)
+                    .on_hover_text(hover)
+                    .clicked()
+                {
+                    picked = Some(p.clone());
+                }
+            }
+        });
+    if let Some(p) = &picked {
+        presets.selected = p.name.clone();
+    }
+    picked
+}
+
+/// Lets the user keep the current settings as a named preset, and share their
+/// presets through a file.
+fn preset_editor(
+    ui: &mut egui::Ui,
+    presets: &mut PresetLibrary,
+    current: impl Fn(&str, &str) -> Preset,
+) {
+    egui::CollapsingHeader::new("Your Presets").show(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut presets.name);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Description");
+            ui.text_edit_singleline(&mut presets.description);
+        });
+        let name = presets.name.trim().to_string();
+        let add = ui
+            .add_enabled(!name.is_empty(), egui::Button::new("Add Current Settings"))
+            .on_hover_text("Replaces any of your presets with the same name");
+        if add.clicked() {
+            let preset = current(&name, presets.description.trim());
+            presets.user.retain(|p| p.name != name);
+            presets.user.push(preset);
+            presets.selected = name;
+            presets.status = Ok(format!("{} presets of your own", presets.user.len()));
+        }
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut presets.path);
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                presets.status = fs::write(&presets.path, Preset::write_list(&presets.user))
+                    .map(|_| format!("saved {} presets", presets.user.len()))
+                    .map_err(|e| e.to_string());
+            }
+            if ui.button("Load").clicked() {
+                presets.status = fs::read_to_string(&presets.path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|s| Preset::parse_list(&s))
+                    .map(|loaded| {
+                        presets.user = loaded;
+                        format!("loaded {} presets", presets.user.len())
+                    });
+            }
+            match &presets.status {
+                Ok(msg) => ui.label(msg),
+                Err(e) => ui.label(RichText::new(e).color(Color32::RED)),
+            };
+        });
+    });
+}
+
+/// Lets the user stack up seed generators, each with its own parameters.
+fn seed_editor(ui: &mut egui::Ui, seed: &mut Seed) {
+    enum Edit {
+        Up(usize),
+        Remove(usize),
+    }
+    let mut edit = None;
+    let count = seed.layers.len();
+    for (i, layer) in seed.layers.iter_mut().enumerate() {
+        egui::CollapsingHeader::new(format!("{}. {}", i + 1, layer.generator.name()))
+            .id_source(("seed layer", i))
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source(("generator", i))
+                        .selected_text(layer.generator.name())
+                        .show_ui(ui, |ui| {
+                            for g in Generator::defaults() {
+                                let selected = g.name() == layer.generator.name();
+                                if ui.selectable_label(selected, g.name()).clicked() && !selected {
+                                    layer.generator = g;
+                                }
+                            }
+                        });
+                    egui::ComboBox::from_id_source(("blend", i))
+                        .selected_text(layer.blend.to_string())
+                        .show_ui(ui, |ui| {
+                            for b in Blend::iter() {
+                                ui.selectable_value(&mut layer.blend, b, b.to_string());
+                            }
+                        });
+                });
+                generator_params(ui, &mut layer.generator, i);
+                ui.horizontal(|ui| {
+                    ui.label("Seed");
+                    ui.add(egui::DragValue::new(&mut layer.seed));
+                    if ui.add_enabled(i > 0, egui::Button::new("Up")).clicked() {
+                        edit = Some(Edit::Up(i));
+                    }
+                    if ui
+                        .add_enabled(count > 1, egui::Button::new("Remove"))
+                        .clicked()
+                    {
+                        edit = Some(Edit::Remove(i));
+                    }
+                });
+            });
+    }
+    match edit {
+        Some(Edit::Up(i)) => seed.layers.swap(i - 1, i),
+        Some(Edit::Remove(i)) => {
+            seed.layers.remove(i);
+        }
+        None => {}
+    }
+    if ui.button("Add Layer").clicked() {
+        seed.layers.push(Layer {
+            generator: Generator::defaults()[0].clone(),
+            seed: count as u32 + 1,
+            blend: Blend::Add,
+        });
+    }
+}
+
+/// The parameter controls of one seed generator.
+fn generator_params(ui: &mut egui::Ui, generator: &mut Generator, id: usize) {
+    match generator {
+        Generator::Random { density } => {
+            ui.add(egui::Slider::new(density, 0. ..=1.).text("Density"));
+        }
+        Generator::Noise {
+            kind,
+            frequency,
+            octaves,
+            threshold,
+        } => {
+            egui::ComboBox::from_id_source(("noise kind", id))
+                .selected_text(kind.to_string())
+                .show_ui(ui, |ui| {
+                    for k in NoiseKind::iter() {
+                        ui.selectable_value(kind, k, k.to_string());
+                    }
+                });
+            ui.add(
+                egui::Slider::new(frequency, 0.01..=1.)
+                    .logarithmic(true)
+                    .text("Frequency"),
+            );
+            ui.add_enabled(
+                *kind == NoiseKind::Fbm,
+                egui::Slider::new(octaves, 1..=8).text("Octaves"),
+            );
+            ui.add(egui::Slider::new(threshold, -1. ..=1.).text("Threshold"));
+        }
+        Generator::Cube { size, hollow } => {
+            ui.add(egui::Slider::new(size, 1..=100).text("Size"));
+            ui.checkbox(hollow, "Hollow");
+        }
+        Generator::Sphere { radius, hollow } => {
+            ui.add(egui::Slider::new(radius, 1. ..=100.).text("Radius"));
+            ui.checkbox(hollow, "Hollow");
+        }
+        Generator::SingleCell => {}
+        Generator::Symmetric { density, radius } => {
+            ui.add(egui::Slider::new(density, 0. ..=1.).text("Density"));
+            ui.add(egui::Slider::new(radius, 1. ..=100.).text("Radius"));
+        }
+    }
+}
+
+/// State of the custom neighborhood editor.
+pub(crate) struct MaskEditor {
+    radius: i8,
+    layer: i8,
+    offsets: BTreeSet<[i8; 3]>,
+}
+
+impl Default for MaskEditor {
+    fn default() -> Self {
+        Self {
+            radius: 1,
+            layer: 0,
+            offsets: Neighbors::Moore(1).offsets().into_iter().collect(),
+        }
+    }
+}
+
+impl MaskEditor {
+    fn neighbors(&self) -> Neighbors {
+        let r = self.radius;
+        Neighbors::custom(
+            self.offsets
+                .iter()
+                .copied()
+                .filter(|o| o.iter().all(|c| c.abs() <= r)),
+        )
+    }
+}
+
+/// Lets the user toggle cells of a custom neighborhood one z layer at a time,
+/// and write the result into the rule string. Returns whether it did.
+fn mask_editor(
+    ui: &mut egui::Ui,
+    mask: &mut MaskEditor,
+    rule_str: &mut String,
+    parsed: &Result<Rule, ParseRuleError>,
+    notation: Notation,
+) -> bool {
+    let mut used = false;
+    egui::CollapsingHeader::new("Neighborhood Editor").show(ui, |ui| {
+        ui.horizontal(|ui| {
+            for (name, n) in [
+                ("Moore", Neighbors::Moore(1)),
+                ("Neumann", Neighbors::Neumann(1)),
+                ("Face+Edge", Neighbors::face_edge()),
+                ("Corners", Neighbors::corners()),
+            ] {
+                if ui.button(name).clicked() {
+                    mask.offsets = n.offsets().into_iter().collect();
+                }
+            }
+            if ui.button("Clear").clicked() {
+                mask.offsets.clear();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut mask.radius, 1, "3×3×3");
+            ui.radio_value(&mut mask.radius, 2, "5×5×5");
+        });
+        let r = mask.radius;
+        mask.layer = mask.layer.clamp(-r, r);
+        ui.add(egui::Slider::new(&mut mask.layer, -r..=r).text("Z Layer"));
+        egui::Grid::new("neighborhood mask")
+            .spacing([2., 2.])
+            .show(ui, |ui| {
+                for y in (-r..=r).rev() {
+                    for x in -r..=r {
+                        let o = [x, y, mask.layer];
+                        if o == [0, 0, 0] {
+                            ui.add_enabled(false, egui::Checkbox::without_text(&mut false));
+                            continue;
+                        }
+                        let mut on = mask.offsets.contains(&o);
+                        if ui.checkbox(&mut on, "").changed() {
+                            if on {
+                                mask.offsets.insert(o);
+                            } else {
+                                mask.offsets.remove(&o);
+                            }
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        let n = mask.neighbors();
+        ui.label(format!("{} neighbors", n.count()));
+        if ui.button("Use in Rule").clicked() {
+            used = true;
+            match parsed {
+                Ok(rule) => {
+                    *rule_str = write_rule(
+                        &Rule {
+                            neighbors: n,
+                            ..rule.clone()
+                        },
+                        notation,
+                    )
+                }
+                Err(_) => {
+                    let head = rule_str.rsplit_once('/').map_or("", |(head, _)| head);
+                    *rule_str = format!("{head}/{n}");
+                }
+            }
+        }
+    });
+    used
+}
@@ -0,0 +1,33 @@
+//! The simulation through the public API alone, as the `headless` binary and
+//! other crates use it without the viewer.
+
+use portfolio_bevy_automata::{Boundary, Grid, NoiseSettings, Rule, Snapshot};
+
+#[test]
+fn runs_and_saves_a_seeded_grid() {
+    let rule = "4/4/5/M".parse::<Rule>().unwrap();
+    let noise = NoiseSettings {
+        size: 6,
+        ..NoiseSettings::default()
+    };
+    let seeded = Grid::new_noise([16, 12, 10], &noise);
+    assert_eq!(seeded.dims(), [16, 12, 10]);
+    assert!(seeded.iter().any(|(_, c)| c.is_live()));
+
+    let grid = (0..5).fold(seeded, |g, _| g.next(&rule, Boundary::Torus));
+    let snapshot = Snapshot {
+        grid,
+        rule,
+        generation: 5,
+    };
+    let mut bytes = Vec::new();
+    snapshot.write(&mut bytes).unwrap();
+    let loaded = Snapshot::read(&mut bytes.as_slice()).unwrap();
+    assert_eq!(loaded.generation, 5);
+    assert_eq!(loaded.rule, snapshot.rule);
+    assert!(loaded.grid.iter().eq(snapshot.grid.iter()));
+
+    // a loaded grid carries on exactly like the one that was saved
+    let next = |s: &Snapshot| s.grid.next(&s.rule, Boundary::Torus);
+    assert!(next(&loaded).iter().eq(next(&snapshot).iter()));
+}